@@ -6,12 +6,13 @@ use yew::prelude::*;
 
 #[derive(Properties, Clone, PartialEq)]
 pub(crate) struct Props {
+    /// the tool in hand, highlighted
+    pub(crate) tool: UsingTool,
     pub(crate) using_tool: Callback<UsingTool>,
 }
 
 #[function_component(KitBar)]
 pub(crate) fn kit_bar(props: &Props) -> Html {
-    let using_tool_state = use_state(|| UsingTool::Select);
     let bar_position_state = use_state(|| 10);
     let is_drug_state = use_state(|| false);
 
@@ -43,61 +44,49 @@ pub(crate) fn kit_bar(props: &Props) -> Html {
     };
 
     let onclick_roll = {
-        let using_tool_state = using_tool_state.clone();
         let using_tool = props.using_tool.clone();
         Callback::from(move |e: MouseEvent| {
             e.prevent_default();
-            using_tool_state.set(UsingTool::Roll);
             using_tool.emit(UsingTool::Roll);
         })
     };
 
     let onclick_stack = {
-        let using_tool_state = using_tool_state.clone();
         let using_tool = props.using_tool.clone();
         Callback::from(move |e: MouseEvent| {
             e.prevent_default();
-            using_tool_state.set(UsingTool::Stack);
             using_tool.emit(UsingTool::Stack);
         })
     };
 
     let onclick_select = {
-        let using_tool_state = using_tool_state.clone();
         let using_tool = props.using_tool.clone();
         Callback::from(move |e: MouseEvent| {
             e.prevent_default();
-            using_tool_state.set(UsingTool::Select);
             using_tool.emit(UsingTool::Select);
         })
     };
 
     let onclick_format_brush = {
-        let using_tool_state = using_tool_state.clone();
         let using_tool = props.using_tool.clone();
         Callback::from(move |e: MouseEvent| {
             e.prevent_default();
-            using_tool_state.set(UsingTool::FormatBrush);
             using_tool.emit(UsingTool::FormatBrush);
         })
     };
 
     let onclick_eyedropper = {
-        let using_tool_state = using_tool_state.clone();
         let using_tool = props.using_tool.clone();
         Callback::from(move |e: MouseEvent| {
             e.prevent_default();
-            using_tool_state.set(UsingTool::Eyedropper);
             using_tool.emit(UsingTool::Eyedropper);
         })
     };
 
     let onclick_delete = {
-        let using_tool_state = using_tool_state.clone();
         let using_tool = props.using_tool.clone();
         Callback::from(move |e: MouseEvent| {
             e.prevent_default();
-            using_tool_state.set(UsingTool::Delete);
             using_tool.emit(UsingTool::Delete);
         })
     };
@@ -132,7 +121,7 @@ pub(crate) fn kit_bar(props: &Props) -> Html {
                     onclick={onclick_roll}
                     class="act_back tools_on_bar"
                     style={
-                        if let UsingTool::Roll = *using_tool_state {
+                        if let UsingTool::Roll = props.tool {
                             "background-color: rgb(63, 68, 83);"
                         } else {""}
                     }
@@ -145,7 +134,7 @@ pub(crate) fn kit_bar(props: &Props) -> Html {
                     onclick={onclick_stack}
                     class="act_back tools_on_bar"
                     style={
-                        if let UsingTool::Stack = *using_tool_state {
+                        if let UsingTool::Stack = props.tool {
                             "background-color: rgb(63, 68, 83);"
                         } else {""}
                     }
@@ -158,7 +147,7 @@ pub(crate) fn kit_bar(props: &Props) -> Html {
                     onclick={onclick_select}
                     class="act_back tools_on_bar"
                     style={
-                        if let UsingTool::Select = *using_tool_state {
+                        if let UsingTool::Select = props.tool {
                             "background-color: rgb(63, 68, 83);"
                         } else {""}
                     }
@@ -171,7 +160,7 @@ pub(crate) fn kit_bar(props: &Props) -> Html {
                     onclick={onclick_format_brush}
                     class="act_back tools_on_bar"
                     style={
-                        if let UsingTool::FormatBrush = *using_tool_state {
+                        if let UsingTool::FormatBrush = props.tool {
                             "background-color: rgb(63, 68, 83);"
                         } else {""}
                     }
@@ -184,7 +173,7 @@ pub(crate) fn kit_bar(props: &Props) -> Html {
                     onclick={onclick_eyedropper}
                     class="act_back tools_on_bar"
                     style={
-                        if let UsingTool::Eyedropper = *using_tool_state {
+                        if let UsingTool::Eyedropper = props.tool {
                             "background-color: rgb(63, 68, 83);"
                         } else {""}
                     }
@@ -197,7 +186,7 @@ pub(crate) fn kit_bar(props: &Props) -> Html {
                     onclick={onclick_delete}
                     class="act_back tools_on_bar"
                     style={
-                        if let UsingTool::Delete = *using_tool_state {
+                        if let UsingTool::Delete = props.tool {
                             "background-color: rgb(63, 68, 83);"
                         } else {""}
                     }
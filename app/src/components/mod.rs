@@ -1,4 +1,5 @@
 pub(super) mod header;
 pub(crate) mod kit_bar;
+pub(super) mod odds;
 pub(super) mod side_menu;
 pub(super) mod img;
\ No newline at end of file
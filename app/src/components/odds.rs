@@ -0,0 +1,90 @@
+use dice::Distribution;
+use std::rc::Rc;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+/// bars lower than this part of the highest are left out of the chart
+const CHART_CUTOFF: f64 = 0.005;
+/// work cap of the chart, computed as the user types, see [`dice::dist::MAX_WORK`]
+const PREVIEW_WORK: u64 = 2_000_000;
+
+#[derive(Properties, Clone, PartialEq)]
+pub(crate) struct Props {
+    pub(crate) expression: String,
+}
+
+/// Chance of the totals of an expression, as a bar chart
+#[function_component(Odds)]
+pub(crate) fn odds(props: &Props) -> Html {
+    let target_state = use_state(|| 10);
+    // the expression it was computed for, again only once that changes
+    let computed = use_mut_ref(|| None::<(String, Rc<Result<Distribution, String>>)>);
+
+    let target_oninput = {
+        let target_state = target_state.clone();
+        Callback::from(move |e: InputEvent| {
+            if let Ok(v) = e.target_unchecked_into::<HtmlInputElement>().value().parse() {
+                target_state.set(v);
+            }
+        })
+    };
+
+    let dist = {
+        let mut computed = computed.borrow_mut();
+        match &*computed {
+            Some((expression, dist)) if *expression == props.expression => dist.clone(),
+            _ => {
+                let dist = Rc::new(distribution(&props.expression));
+                *computed = Some((props.expression.clone(), dist.clone()));
+                dist
+            }
+        }
+    };
+    let dist = match &*dist {
+        Ok(dist) => dist,
+        Err(e) => return html!(<div class="odds">{e}</div>),
+    };
+
+    let target = *target_state;
+    let peak = dist.iter().map(|(_, p)| p).fold(0.0, f64::max);
+    let bars = dist
+        .iter()
+        .filter(|(_, p)| *p >= peak * CHART_CUTOFF)
+        .map(|(v, p)| {
+            html!(
+                <div
+                    class={classes!("odds_bar", (v >= target).then_some("beats"))}
+                    title={format!("{}: {:.2}%", v, p * 100.0)}
+                    style={format!("height: {}%;", p / peak * 100.0)}
+                ></div>
+            )
+        });
+
+    html!(
+        <div class="odds">
+            <div class="odds_stats">
+                <span>{format!("mean {:.2}", dist.mean())}</span>
+                <span>{format!("σ {:.2}", dist.std_dev())}</span>
+            </div>
+            <div class="odds_stats">
+                <span>{"P(≥"}</span>
+                <input
+                    type="number"
+                    title="Total to beat"
+                    value={target.to_string()}
+                    oninput={target_oninput}
+                />
+                <span>{format!(") = {:.2}%", dist.at_least(target) * 100.0)}</span>
+            </div>
+            <div class="odds_chart">{ for bars }</div>
+        </div>
+    )
+}
+
+/// the distribution to chart, or why there's none
+fn distribution(expression: &str) -> Result<Distribution, String> {
+    dice::parse(expression)
+        .map_err(|e| e.to_string())?
+        .distribution_within(PREVIEW_WORK)
+        .ok_or_else(|| "Too many outcomes to chart".to_owned())
+}
@@ -1,9 +1,34 @@
+use dice::roll::TermRoll;
+use dice::roll_log::{RollEntry, RollLog};
+use dice::expr::Sign;
+use scenes::{Style, StyleId, StyleRegistry};
+use std::rc::Rc;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
 use yew::prelude::*;
 use super::img::*;
+use super::odds::Odds;
+
+#[derive(Properties, Clone, PartialEq)]
+pub(crate) struct Props {
+    pub(crate) styles: Rc<StyleRegistry>,
+    /// style the Stack tool places
+    pub(crate) brush: StyleId,
+    pub(crate) on_brush: Callback<StyleId>,
+    pub(crate) roll_log: RollLog,
+    /// who rolls on this client
+    pub(crate) player: String,
+    /// expression the Roll tool rolls
+    pub(crate) expression: String,
+    pub(crate) on_player: Callback<String>,
+    pub(crate) on_expression: Callback<String>,
+    pub(crate) on_reroll: Callback<usize>,
+}
 
 #[function_component(SideMenu)]
-pub(crate) fn side_menu() -> Html {
+pub(crate) fn side_menu(props: &Props) -> Html {
     let is_menu_hide = use_state(|| false);
+    // None to show every player
+    let filter_state = use_state(|| None::<String>);
 
     let onclick = {
         let is_menu_hide = is_menu_hide.clone();
@@ -12,6 +37,51 @@ pub(crate) fn side_menu() -> Html {
         })
     };
 
+    let player_oninput = {
+        let on_player = props.on_player.clone();
+        Callback::from(move |e: InputEvent| {
+            on_player.emit(e.target_unchecked_into::<HtmlInputElement>().value());
+        })
+    };
+
+    let expression_oninput = {
+        let on_expression = props.on_expression.clone();
+        Callback::from(move |e: InputEvent| {
+            on_expression.emit(e.target_unchecked_into::<HtmlInputElement>().value());
+        })
+    };
+
+    let brush_onchange = {
+        let on_brush = props.on_brush.clone();
+        Callback::from(move |e: Event| {
+            on_brush.emit(e.target_unchecked_into::<HtmlSelectElement>().value().into());
+        })
+    };
+
+    let filter_onchange = {
+        let filter_state = filter_state.clone();
+        Callback::from(move |e: Event| {
+            let v = e.target_unchecked_into::<HtmlSelectElement>().value();
+            filter_state.set(if v.is_empty() { None } else { Some(v) });
+        })
+    };
+
+    let mut styles: Vec<_> = props.styles.iter().map(|(id, _)| id).collect();
+    // a brush picked from the map may not be registered
+    if !props.styles.contains(&props.brush) {
+        styles.push(&props.brush);
+    }
+    styles.sort();
+    let brush_style = props.styles.get(&props.brush);
+
+    let players = props.roll_log.players();
+    let entries: Html = props
+        .roll_log
+        .filter(filter_state.as_deref())
+        .rev()
+        .map(|e| roll_entry(e, &props.on_reroll))
+        .collect();
+
     html!(
         <>
             <div
@@ -29,9 +99,143 @@ pub(crate) fn side_menu() -> Html {
                     width: 300px;
                     min-width: 300px;
                     display: {};
+                    flex-direction: column;
                     background-color: rgb(33, 37, 43);
+                    color: rgb(171, 178, 191);
                 ", if *is_menu_hide {"none"} else {"flex"})}
-            ></div>
+            >
+                <div class="brush">
+                    <span
+                        class="brush_swatch"
+                        style={format!("background-color: {};", swatch(brush_style.map(|s| s.tint())))}
+                    />
+                    <select title="Style the Stack tool places" onchange={brush_onchange}>
+                        {
+                            for styles.iter().map(|id| html!(
+                                <option value={id.to_string()} selected={**id == props.brush}>{id}</option>
+                            ))
+                        }
+                    </select>
+                </div>
+                <div class="brush_info">{brush_info(brush_style)}</div>
+                <div class="roll_form">
+                    <input
+                        title="Player"
+                        value={props.player.clone()}
+                        oninput={player_oninput}
+                    />
+                    <input
+                        title="Expression the Roll tool rolls"
+                        value={props.expression.clone()}
+                        oninput={expression_oninput}
+                    />
+                    <select onchange={filter_onchange}>
+                        <option value="" selected={filter_state.is_none()}>{"All"}</option>
+                        {
+                            for players.iter().map(|p| html!(
+                                <option
+                                    value={p.to_string()}
+                                    selected={filter_state.as_deref() == Some(*p)}
+                                >{p}</option>
+                            ))
+                        }
+                    </select>
+                </div>
+                <Odds expression={props.expression.clone()} />
+                <div class="roll_log">{entries}</div>
+            </div>
+        </>
+    )
+}
+
+/// css color of a tint, grey as missing styles are drawn
+fn swatch(tint: Option<[u8; 4]>) -> String {
+    let [r, g, b, a] = tint.unwrap_or([204, 204, 204, 255]);
+
+    format!("rgba({}, {}, {}, {})", r, g, b, a as f32 / 255.0)
+}
+
+/// what the brush places, at a glance
+fn brush_info(style: Option<&Style>) -> String {
+    let style = match style {
+        Some(s) => s,
+        None => return "not registered".to_owned(),
+    };
+    let mut info = vec![style.model().to_owned()];
+
+    if let Some(t) = style.texture() {
+        info.push(t.to_owned());
+    }
+    info.push(if style.passable() { "passable" } else { "solid" }.to_owned());
+    if style.light() > 0.0 {
+        info.push(format!("light {}", style.light()));
+    }
+
+    info.join(" · ")
+}
+
+fn roll_entry(entry: &RollEntry, on_reroll: &Callback<usize>) -> Html {
+    let onclick = {
+        let id = entry.id();
+        let on_reroll = on_reroll.clone();
+        Callback::from(move |e: MouseEvent| {
+            e.prevent_default();
+            on_reroll.emit(id);
+        })
+    };
+
+    let roll = entry.roll();
+    // only committed rolls can be trusted,
+    // the dice themselves are checked against the map they landed on when received
+    let verified = entry.commitment().map(|c| {
+        if c.opens(roll) {
+            html!(<span class="roll_verified" title="Opens its commitment">{"✓"}</span>)
+        } else {
+            html!(<span class="roll_verified failed" title="Does not open its commitment">{"✗"}</span>)
+        }
+    });
+
+    html!(
+        <div class="roll_entry" title={format!("seed {} / {}", roll.seed(), roll.hash_hex())}>
+            <div class="roll_head">
+                <span>{entry.player()}</span>
+                {for verified}
+                <span class="roll_expression">
+                    {roll.expr().to_string()}
+                    {entry.reroll_of().map(|id| format!(" (re-roll of #{})", id)).unwrap_or_default()}
+                </span>
+                <span title="Re-roll" class="act_back roll_again" {onclick}>{"↻"}</span>
+            </div>
+            <div class="roll_terms">
+                { for roll.terms().iter().enumerate().map(|(i, t)| term(i, t)) }
+                <span class="roll_total">{format!("= {}", roll.total())}</span>
+            </div>
+        </div>
+    )
+}
+
+fn term(i: usize, t: &TermRoll) -> Html {
+    let sign = match (i, t.sign()) {
+        (0, Sign::Plus) => "",
+        (_, Sign::Plus) => "+",
+        (_, Sign::Minus) => "-",
+    };
+
+    if let Some(c) = t.constant() {
+        return html!(<span class="roll_modifier">{format!("{}{}", sign, c)}</span>);
+    }
+
+    html!(
+        <>
+            <span>{sign}</span>
+            {
+                for t.dice().iter().map(|d| html!(
+                    <span
+                        class={classes!("roll_die", (!d.kept()).then_some("dropped"), d.exploded().then_some("exploded"))}
+                        title={format!("d{}", d.sides())}
+                    >{d.to_string()}</span>
+                ))
+            }
         </>
     )
 }
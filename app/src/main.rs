@@ -1,4 +1,8 @@
-use gloo::console::log;
+use dice::verify::Commitment;
+use gloo::{console::log, events::EventListener};
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlInputElement, HtmlSelectElement, KeyboardEvent};
 use yew::prelude::*;
 
 /// parts of pages
@@ -14,9 +18,227 @@ mod state;
 
 #[function_component(App)]
 fn app() -> Html {
-    let using_tool = Callback::from(|u| {
-        log!(format!("{:?}", u));
-    });
+    let editor = use_mut_ref(state::editor::Editor::default);
+    // revision of the map the sketchpad draws, so it draws again after each edit
+    let revision = use_state(|| editor.borrow().scene().revision());
+    let styles_view = use_state(|| Rc::new(editor.borrow().styles().clone()));
+    let selection_view = use_state(|| Rc::new(editor.borrow().selection().clone()));
+
+    {
+        let editor = editor.clone();
+        let revision = revision.clone();
+        let selection_view = selection_view.clone();
+        use_effect_with_deps(
+            move |_| {
+                // undo, redo and the selection, see `shortcut`
+                let listener = EventListener::new(&gloo::utils::document(), "keydown", move |e| {
+                    let e = match e.dyn_ref::<KeyboardEvent>() {
+                        Some(e) => e,
+                        None => return,
+                    };
+                    let mut editor = editor.borrow_mut();
+                    let changed = match shortcut(&mut editor, e) {
+                        Some(changed) => changed,
+                        None => return,
+                    };
+
+                    e.prevent_default();
+
+                    if changed {
+                        revision.set(editor.scene().revision());
+                        selection_view.set(Rc::new(editor.selection().clone()));
+                    }
+                });
+
+                move || drop(listener)
+            },
+            (),
+        );
+    }
+
+    let tool = use_state(|| state::using_tool::UsingTool::Select);
+    // back to it after the Eyedropper picked
+    let previous_tool = use_state(|| state::using_tool::UsingTool::Select);
+    // style the Stack tool places
+    let brush = use_state(|| scenes::StyleId::from("stone"));
+    // style the FormatBrush copies, picked by its first click
+    let format_source = use_state(|| None::<scenes::StyleId>);
+    let roll_log = use_state(dice::RollLog::default);
+    let player = use_state(|| "Player".to_owned());
+    let expression = use_state(|| "d20".to_owned());
+
+    let using_tool = {
+        let editor = editor.clone();
+        let tool = tool.clone();
+        let previous_tool = previous_tool.clone();
+        let format_source = format_source.clone();
+        let roll_log = roll_log.clone();
+        let player = player.clone();
+        let expression = expression.clone();
+        Callback::from(move |u| {
+            match u {
+                // throws onto the map right away, the tool in hand stays,
+                // committed to before the dice are known so any client can check it
+                state::using_tool::UsingTool::Roll => match dice::parse(&expression) {
+                    Ok(expr) => {
+                        let editor = editor.borrow();
+                        let secret = state::random::random_secret();
+                        // no other players to salt it yet, this client's salt stands in
+                        let roll = expr.reveal_thrown(
+                            secret,
+                            state::random::random_secret().to_vec(),
+                            editor.scene().oc_tree(),
+                            editor.throw_origin(),
+                        );
+
+                        let mut log = (*roll_log).clone();
+                        log.push_committed(player.as_str(), Commitment::new(&secret), roll);
+                        roll_log.set(log);
+                    }
+                    Err(e) => log!(format!("{} in `{}`", e, *expression)),
+                },
+                state::using_tool::UsingTool::Eyedropper => {
+                    if !matches!(*tool, state::using_tool::UsingTool::Eyedropper) {
+                        previous_tool.set(*tool);
+                    }
+                    tool.set(u);
+                }
+                // picks a new source each time it's taken
+                state::using_tool::UsingTool::FormatBrush => {
+                    format_source.set(None);
+                    tool.set(u);
+                }
+                _ => tool.set(u),
+            }
+
+            log!(format!(
+                "{:?} on a map of {} instances",
+                u,
+                editor.borrow().scene().len()
+            ));
+        })
+    };
+
+    let on_pick = {
+        let editor = editor.clone();
+        let revision = revision.clone();
+        let selection_view = selection_view.clone();
+        let tool = tool.clone();
+        let previous_tool = previous_tool.clone();
+        let brush = brush.clone();
+        let format_source = format_source.clone();
+        Callback::from(move |e: sketchpad::PickEvent| {
+            use sketchpad::Stroke;
+            use state::using_tool::UsingTool;
+
+            let mut editor = editor.borrow_mut();
+            let changed = match (*tool, e.stroke(), e.pick()) {
+                // click selects one, Shift adds or takes it, drag adds, the ground clears
+                (UsingTool::Select, Stroke::Begin, Some(pick)) => {
+                    match pick.instance() {
+                        Some(v) => editor.select(v.pos(), e.shift()),
+                        None if e.shift() => {}
+                        None => {
+                            editor.clear_selection();
+                        }
+                    }
+                    selection_view.set(Rc::new(editor.selection().clone()));
+                    false
+                }
+                (UsingTool::Select, Stroke::Drag, Some(pick)) => {
+                    if pick.instance().is_some_and(|v| editor.select_add(v.pos())) {
+                        selection_view.set(Rc::new(editor.selection().clone()));
+                    }
+                    false
+                }
+                (UsingTool::Eyedropper, Stroke::Begin, Some(pick)) => {
+                    if let Some(v) = pick.instance() {
+                        brush.set(v.style_id().clone());
+                        tool.set(*previous_tool);
+                    }
+                    false
+                }
+                // click stacks one on the face, drag a box on its plane, Shift a row
+                (UsingTool::Stack, Stroke::Begin, Some(pick)) => {
+                    editor.stack_begin(pick.adjacent(), pick.face(), (*brush).clone());
+                    true
+                }
+                (UsingTool::Stack, Stroke::Drag, _) => editor.stack_drag(e.ray(), e.shift()),
+                // first click picks the source, then clicks and drags copy it, Shift floods
+                (UsingTool::FormatBrush, Stroke::Begin, Some(pick)) => {
+                    match (&*format_source, pick.instance()) {
+                        (None, Some(v)) => {
+                            format_source.set(Some(v.style_id().clone()));
+                            false
+                        }
+                        (Some(source), Some(v)) => {
+                            editor.format_begin(v.pos(), source.clone(), e.shift());
+                            true
+                        }
+                        _ => false,
+                    }
+                }
+                (UsingTool::FormatBrush, Stroke::Drag, Some(pick)) => pick
+                    .instance()
+                    .is_some_and(|v| editor.format_drag(v.pos())),
+                // click removes one, drag a box on its layer, Shift the region of its style
+                (UsingTool::Delete, Stroke::Begin, Some(pick)) => match pick.instance() {
+                    Some(v) => {
+                        editor.delete_begin(v.pos(), e.shift());
+                        true
+                    }
+                    None => false,
+                },
+                (UsingTool::Delete, Stroke::Drag, _) => editor.delete_drag(e.ray()),
+                (_, Stroke::End, _) => {
+                    editor.end_stroke();
+                    false
+                }
+                _ => false,
+            };
+
+            if changed {
+                revision.set(editor.scene().revision());
+            }
+        })
+    };
+
+    let on_brush = {
+        let brush = brush.clone();
+        Callback::from(move |v| brush.set(v))
+    };
+
+    let on_reroll = {
+        let editor = editor.clone();
+        let roll_log = roll_log.clone();
+        Callback::from(move |id| {
+            let editor = editor.borrow();
+            let secret = state::random::random_secret();
+            let mut log = (*roll_log).clone();
+            let rerolled = log.reroll_committed(id, Commitment::new(&secret), |expr| {
+                expr.reveal_thrown(
+                    secret,
+                    state::random::random_secret().to_vec(),
+                    editor.scene().oc_tree(),
+                    editor.throw_origin(),
+                )
+            });
+
+            if rerolled.is_some() {
+                roll_log.set(log);
+            }
+        })
+    };
+
+    let on_player = {
+        let player = player.clone();
+        Callback::from(move |v| player.set(v))
+    };
+
+    let on_expression = {
+        let expression = expression.clone();
+        Callback::from(move |v| expression.set(v))
+    };
 
     html!(
         <>
@@ -30,17 +252,74 @@ fn app() -> Html {
                     margin: 0;
                 "
             >
-                <sketchpad::Sketchpad />
+                <sketchpad::Sketchpad
+                    scene={editor.borrow().shared_scene()}
+                    revision={*revision}
+                    styles={(*styles_view).clone()}
+                    selection={(*selection_view).clone()}
+                    {on_pick}
+                />
                 <div style="height: 100%; width: 100%; margin: 0 0 0 -100%;">
-                    <components::kit_bar::KitBar {using_tool}/>
+                    <components::kit_bar::KitBar tool={*tool} {using_tool}/>
                     <div style="height: 100%; width: 100%"></div>
-                    <components::side_menu::SideMenu />
+                    <components::side_menu::SideMenu
+                        styles={(*styles_view).clone()}
+                        brush={(*brush).clone()}
+                        {on_brush}
+                        roll_log={(*roll_log).clone()}
+                        player={(*player).clone()}
+                        expression={(*expression).clone()}
+                        {on_player}
+                        {on_expression}
+                        {on_reroll}
+                    />
                 </div>
             </div>
         </>
     )
 }
 
+/// Ctrl+Z to undo, Ctrl+Shift+Z to redo,
+/// and out of text fields, the selection as a block:
+/// arrows and PageUp/PageDown move, R turns (Shift clockwise),
+/// M mirrors along x (Shift y), Ctrl+D duplicates,
+/// Delete removes and Escape clears it
+///
+/// None if not a shortcut, else whether the editor changed
+fn shortcut(editor: &mut state::editor::Editor, e: &KeyboardEvent) -> Option<bool> {
+    use cgmath::Vector3;
+    use state::editor::Transform;
+
+    let ctrl = e.ctrl_key() || e.meta_key();
+    let typing = e
+        .target()
+        .is_some_and(|t| t.has_type::<HtmlInputElement>() || t.has_type::<HtmlSelectElement>());
+    let step = |x, y, z| Transform::Move(Vector3::new(x, y, z));
+    let shift = e.shift_key();
+
+    let transform = match e.key().as_str() {
+        "z" | "Z" if ctrl && shift => return Some(editor.redo()),
+        "z" | "Z" if ctrl => return Some(editor.undo()),
+        _ if typing => return None,
+        "d" | "D" if ctrl => Transform::Duplicate,
+        _ if ctrl => return None,
+        "ArrowLeft" => step(-1, 0, 0),
+        "ArrowRight" => step(1, 0, 0),
+        "ArrowUp" => step(0, 1, 0),
+        "ArrowDown" => step(0, -1, 0),
+        "PageUp" => step(0, 0, 1),
+        "PageDown" => step(0, 0, -1),
+        "r" | "R" => Transform::Rotate(if shift { -1 } else { 1 }),
+        "m" | "M" if shift => Transform::Mirror(scenes::Axis::Y),
+        "m" | "M" => Transform::Mirror(scenes::Axis::X),
+        "Delete" | "Backspace" => Transform::Delete,
+        "Escape" => return Some(editor.clear_selection()),
+        _ => return None,
+    };
+
+    Some(editor.transform(transform))
+}
+
 fn main() {
     yew::start_app::<App>();
 }
@@ -0,0 +1,264 @@
+use cgmath::{Point3, Vector3};
+use scenes::{
+    tools::{
+        delete::{self, Delete},
+        format_brush::FormatBrush,
+        stack::Stack,
+    },
+    Axis, History, Scene, Selection, Style, StyleId, StyleRegistry,
+};
+use std::{
+    cell::{Ref, RefCell},
+    rc::Rc,
+};
+
+/// The map under editing, with its styles and undo history
+#[derive(Debug)]
+pub(crate) struct Editor {
+    /// shared with the view, which draws it as it is
+    scene: Rc<RefCell<Scene>>,
+    styles: StyleRegistry,
+    history: History,
+    stroke: Option<Stroke>,
+    selection: Selection,
+}
+
+/// A tool stroke under way
+#[derive(Debug)]
+enum Stroke {
+    Stack(Stack),
+    FormatBrush(FormatBrush),
+    Delete(Delete),
+}
+
+/// What's done to the selection as a block, one undo step each
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Transform {
+    Move(Vector3<i32>),
+    /// quarter turns, counter-clockwise seen from above
+    Rotate(i32),
+    Mirror(Axis),
+    /// a copy right beside it along +x, selected in its place
+    Duplicate,
+    Delete,
+}
+
+impl Editor {
+    pub(crate) fn scene(&self) -> Ref<'_, Scene> {
+        self.scene.borrow()
+    }
+
+    /// the map itself, for the view to draw without a copy
+    pub(crate) fn shared_scene(&self) -> Rc<RefCell<Scene>> {
+        self.scene.clone()
+    }
+
+    pub(crate) fn styles(&self) -> &StyleRegistry {
+        &self.styles
+    }
+
+    pub(crate) fn selection(&self) -> &Selection {
+        &self.selection
+    }
+
+    /// Where the Roll tool throws dice from, two voxels over the top
+    /// of the first tile selected, or of the middle of the map
+    pub(crate) fn throw_origin(&self) -> Point3<f64> {
+        let scene = self.scene.borrow();
+        let tree = scene.oc_tree();
+        let column = self
+            .selection
+            .iter()
+            .next()
+            .unwrap_or_else(|| tree.central());
+        let scope = tree.scope() as f32;
+
+        let floor = tree
+            .raycast(
+                Point3::new(
+                    column.x as f32 + 0.5,
+                    column.y as f32 + 0.5,
+                    tree.central().z as f32 + scope,
+                ),
+                -Vector3::unit_z(),
+                2.0 * scope + 1.0,
+            )
+            .map_or(column.z, |hit| hit.instance().pos().z + 1);
+
+        Point3::new(
+            column.x as f64 + 0.5,
+            column.y as f64 + 0.5,
+            floor as f64 + 2.0,
+        )
+    }
+
+    /// select pos alone, or add or take it from the selection with `toggle`
+    pub(crate) fn select(&mut self, pos: Point3<i32>, toggle: bool) {
+        if toggle {
+            self.selection.toggle(pos);
+        } else {
+            self.selection.clear();
+            self.selection.insert(pos);
+        }
+    }
+
+    /// false if already selected
+    pub(crate) fn select_add(&mut self, pos: Point3<i32>) -> bool {
+        self.selection.insert(pos)
+    }
+
+    /// false if nothing was selected
+    pub(crate) fn clear_selection(&mut self) -> bool {
+        let changed = !self.selection.is_empty();
+        self.selection.clear();
+        changed
+    }
+
+    /// false if nothing selected, a stroke under way is ended first
+    pub(crate) fn transform(&mut self, transform: Transform) -> bool {
+        self.end_stroke();
+
+        let (min, max) = match self.selection.bounds() {
+            Some(b) => b,
+            None => return false,
+        };
+        let mut scene = self.scene.borrow_mut();
+        let (scene, history) = (&mut *scene, &mut self.history);
+
+        match transform {
+            Transform::Move(offset) => self.selection.translate(scene, history, offset),
+            Transform::Rotate(turns) => self.selection.rotate(scene, history, turns),
+            Transform::Mirror(axis) => self.selection.mirror(scene, history, axis),
+            Transform::Duplicate => {
+                self.selection
+                    .duplicate(scene, history, Vector3::new(max.x - min.x + 1, 0, 0))
+            }
+            Transform::Delete => self.selection.delete(scene, history),
+        }
+        true
+    }
+
+    /// false if nothing to undo, a stroke under way is ended first
+    pub(crate) fn undo(&mut self) -> bool {
+        self.end_stroke();
+        self.history.undo(&mut self.scene.borrow_mut())
+    }
+
+    /// false if nothing to redo, a stroke under way is ended first
+    pub(crate) fn redo(&mut self) -> bool {
+        self.end_stroke();
+        self.history.redo(&mut self.scene.borrow_mut())
+    }
+
+    /// start a Stack stroke in front of a face
+    pub(crate) fn stack_begin(&mut self, cell: Point3<i32>, face: Vector3<i32>, style_id: StyleId) {
+        self.end_stroke();
+        self.stroke = Some(Stroke::Stack(Stack::begin(
+            &mut self.scene.borrow_mut(),
+            cell,
+            face,
+            style_id,
+        )));
+    }
+
+    /// false if no Stack stroke under way
+    pub(crate) fn stack_drag(
+        &mut self,
+        (origin, direction): (Point3<f32>, Vector3<f32>),
+        row_only: bool,
+    ) -> bool {
+        match &mut self.stroke {
+            Some(Stroke::Stack(stack)) => {
+                stack.drag(&mut self.scene.borrow_mut(), origin, direction, row_only);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Start a FormatBrush stroke copying `style_id`, painting at pos,
+    /// or all the region connected to it with `flood`
+    pub(crate) fn format_begin(&mut self, pos: Point3<i32>, style_id: StyleId, flood: bool) {
+        self.end_stroke();
+
+        let mut brush = FormatBrush::begin(style_id);
+        if flood {
+            brush.flood(&mut self.scene.borrow_mut(), pos);
+        } else {
+            brush.paint(&mut self.scene.borrow_mut(), pos);
+        }
+        self.stroke = Some(Stroke::FormatBrush(brush));
+    }
+
+    /// false if nothing restyled
+    pub(crate) fn format_drag(&mut self, pos: Point3<i32>) -> bool {
+        match &mut self.stroke {
+            Some(Stroke::FormatBrush(brush)) => brush.paint(&mut self.scene.borrow_mut(), pos),
+            _ => false,
+        }
+    }
+
+    /// Start a Delete stroke at pos,
+    /// or remove all the region connected to it with `flood`, as one undo step
+    pub(crate) fn delete_begin(&mut self, pos: Point3<i32>, flood: bool) {
+        self.end_stroke();
+
+        if flood {
+            delete::delete_connected(&mut self.scene.borrow_mut(), &mut self.history, pos);
+        } else {
+            self.stroke = Some(Stroke::Delete(Delete::begin(
+                &mut self.scene.borrow_mut(),
+                pos,
+            )));
+        }
+    }
+
+    /// false if no Delete stroke under way
+    pub(crate) fn delete_drag(&mut self, (origin, direction): (Point3<f32>, Vector3<f32>)) -> bool {
+        match &mut self.stroke {
+            Some(Stroke::Delete(stroke)) => {
+                stroke.drag(&mut self.scene.borrow_mut(), origin, direction);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// record the stroke under way as one undo step
+    pub(crate) fn end_stroke(&mut self) {
+        match self.stroke.take() {
+            Some(Stroke::Stack(stack)) => {
+                stack.end(&mut self.scene.borrow_mut(), &mut self.history)
+            }
+            Some(Stroke::FormatBrush(brush)) => {
+                brush.end(&mut self.scene.borrow_mut(), &mut self.history)
+            }
+            Some(Stroke::Delete(stroke)) => stroke.end(&mut self.history),
+            None => {}
+        }
+    }
+}
+
+/// an empty map, with a few styles to start with
+impl Default for Editor {
+    fn default() -> Self {
+        let mut styles = StyleRegistry::new();
+        let cube = || Style::new("cube.obj".to_owned());
+
+        styles.register("stone", cube().with_tint([152, 152, 160, 255]));
+        styles.register("grass", cube().with_tint([110, 170, 90, 255]));
+        styles.register("wood", cube().with_tint([160, 115, 70, 255]));
+        styles.register(
+            "water",
+            cube().with_tint([70, 130, 200, 255]).with_passable(true),
+        );
+
+        Self {
+            scene: Rc::new(RefCell::new(Scene::default())),
+            styles,
+            history: History::default(),
+            stroke: None,
+            selection: Selection::new(),
+        }
+    }
+}
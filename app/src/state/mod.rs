@@ -1 +1,3 @@
-pub(crate) mod using_tool;
\ No newline at end of file
+pub(crate) mod editor;
+pub(crate) mod random;
+pub(crate) mod using_tool;
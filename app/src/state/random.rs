@@ -0,0 +1,13 @@
+/// Secret to commit to before a roll, or salt for one, see [`dice::verify`]
+///
+/// From the browser's crypto, others can't guess it from the commitment.
+pub(crate) fn random_secret() -> [u8; 32] {
+    let mut ans = [0; 32];
+
+    gloo::utils::window()
+        .crypto()
+        .and_then(|c| c.get_random_values_with_u8_array(&mut ans))
+        .expect("crypto.getRandomValues in every browser the sketchpad runs on");
+
+    ans
+}
@@ -0,0 +1,434 @@
+use crate::{
+    expr::{Dice, Expr, Keep, Sign, Term},
+    roll::EXPLODE_DEPTH,
+};
+
+/// most distinct totals a distribution is computed for
+pub const MAX_OUTCOMES: usize = 100_000;
+/// rough count of multiply-adds a distribution is computed with
+pub const MAX_WORK: u64 = 200_000_000;
+
+/// Exact probability of every total of an expression
+///
+/// # Example
+/// ```
+/// // what's the chance 2d6+3 beats 10?
+/// let dist = dice::parse("2d6+3").unwrap().distribution().unwrap();
+///
+/// assert!((dist.at_least(10) - 21.0 / 36.0).abs() < 1e-12);
+/// assert!((dist.mean() - 10.0).abs() < 1e-12);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Distribution {
+    min: i64,
+    /// probability of `min + i`
+    probs: Vec<f64>,
+}
+
+impl Distribution {
+    pub fn constant(v: i64) -> Self {
+        Self {
+            min: v,
+            probs: vec![1.0],
+        }
+    }
+
+    /// one die, exploding at most `explode_depth` times, as it's rolled
+    pub fn die(sides: u32, explode: bool, explode_depth: usize) -> Self {
+        let p = 1.0 / sides as f64;
+
+        if !explode || sides == 1 {
+            return Self {
+                min: 1,
+                probs: vec![p; sides as usize],
+            };
+        }
+
+        let s = sides as usize;
+        let mut probs = vec![0.0; (explode_depth + 1) * s];
+        let mut chain = p;
+
+        // k max rolls, then a roll below max
+        for k in 0..explode_depth {
+            for r in 1..s {
+                probs[k * s + r - 1] = chain;
+            }
+            chain *= p;
+        }
+        // the last roll allowed, whatever it is
+        for r in 1..=s {
+            probs[explode_depth * s + r - 1] = chain;
+        }
+
+        Self { min: 1, probs }
+    }
+
+    pub fn min(&self) -> i64 {
+        self.min
+    }
+
+    pub fn max(&self) -> i64 {
+        self.min + self.probs.len() as i64 - 1
+    }
+
+    /// P(total = v)
+    pub fn probability(&self, v: i64) -> f64 {
+        usize::try_from(v - self.min)
+            .ok()
+            .and_then(|i| self.probs.get(i))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// P(total ≥ v)
+    pub fn at_least(&self, v: i64) -> f64 {
+        let from = (v - self.min).clamp(0, self.probs.len() as i64) as usize;
+
+        self.probs[from..].iter().sum()
+    }
+
+    /// P(total ≤ v)
+    pub fn at_most(&self, v: i64) -> f64 {
+        let to = (v - self.min + 1).clamp(0, self.probs.len() as i64) as usize;
+
+        self.probs[..to].iter().sum()
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.iter().map(|(v, p)| v as f64 * p).sum()
+    }
+
+    pub fn variance(&self) -> f64 {
+        let mean = self.mean();
+
+        self.iter()
+            .map(|(v, p)| (v as f64 - mean).powi(2) * p)
+            .sum()
+    }
+
+    pub fn std_dev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    /// every total from min to max with its probability, zeros included
+    pub fn iter(&self) -> impl Iterator<Item = (i64, f64)> + '_ {
+        self.probs
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (self.min + i as i64, *p))
+    }
+
+    /// distribution of the sum of both
+    fn convolve(&self, other: &Self) -> Self {
+        let mut probs = vec![0.0; self.probs.len() + other.probs.len() - 1];
+
+        for (i, a) in self.probs.iter().enumerate().filter(|(_, a)| **a != 0.0) {
+            for (j, b) in other.probs.iter().enumerate() {
+                probs[i + j] += a * b;
+            }
+        }
+
+        Self {
+            min: self.min + other.min,
+            probs,
+        }
+    }
+
+    fn negate(mut self) -> Self {
+        self.min = -self.max();
+        self.probs.reverse();
+        self
+    }
+
+    /// drop the impossible totals at both ends
+    fn trim(mut self) -> Self {
+        let start = self.probs.iter().position(|p| *p != 0.0).unwrap_or(0);
+        let end = self
+            .probs
+            .iter()
+            .rposition(|p| *p != 0.0)
+            .map_or(1, |e| e + 1);
+
+        self.min += start as i64;
+        self.probs = self.probs[start..end].to_vec();
+        self
+    }
+}
+
+impl Expr {
+    /// exact distribution of the total, as [`Expr::roll`] rolls it,
+    /// None if too large to compute, see [`MAX_OUTCOMES`] and [`MAX_WORK`]
+    pub fn distribution(&self) -> Option<Distribution> {
+        self.distribution_with_depth(EXPLODE_DEPTH)
+    }
+
+    /// as [`Expr::distribution`], with dice exploding at most `explode_depth` times
+    pub fn distribution_with_depth(&self, explode_depth: usize) -> Option<Distribution> {
+        self.compute_distribution(explode_depth, MAX_WORK)
+    }
+
+    /// as [`Expr::distribution`], None past `max_work` instead of [`MAX_WORK`],
+    /// for a preview that must not keep the user waiting
+    pub fn distribution_within(&self, max_work: u64) -> Option<Distribution> {
+        self.compute_distribution(EXPLODE_DEPTH, max_work.min(MAX_WORK))
+    }
+
+    fn compute_distribution(&self, explode_depth: usize, max_work: u64) -> Option<Distribution> {
+        let mut outcomes: u64 = 1;
+        let mut work: u64 = 0;
+
+        for (_, term) in self.terms() {
+            if let Term::Dice(d) = term {
+                let (o, w) = cost(d, explode_depth)?;
+                outcomes = outcomes.checked_add(o - 1)?;
+                work = work.checked_add(w)?;
+            }
+        }
+
+        if outcomes > MAX_OUTCOMES as u64 || work > max_work {
+            return None;
+        }
+
+        let ans = self
+            .terms()
+            .iter()
+            .map(|(sign, term)| {
+                let d = match term {
+                    Term::Constant(c) => Distribution::constant(*c as i64),
+                    Term::Dice(d) => dice_distribution(d, explode_depth),
+                };
+
+                match sign {
+                    Sign::Plus => d,
+                    Sign::Minus => d.negate(),
+                }
+            })
+            .fold(Distribution::constant(0), |acc, d| acc.convolve(&d));
+
+        Some(ans)
+    }
+}
+
+/// distinct totals and work of a group of dice,
+/// None if they overflow, far past any limit anyway
+fn cost(d: &Dice, explode_depth: usize) -> Option<(u64, u64)> {
+    let die = if d.explode() {
+        (explode_depth as u64 + 1).checked_mul(d.sides() as u64)?
+    } else {
+        d.sides() as u64
+    };
+    let outcomes = (d.kept() as u64).checked_mul(die - 1)?.checked_add(1)?;
+    let count = d.count() as u64;
+
+    let work = match d.keep() {
+        Some(_) => die.checked_mul(count)?.checked_mul(count)?,
+        None => die.checked_mul(count)?,
+    }
+    .checked_mul(outcomes)?;
+
+    Some((outcomes, work))
+}
+
+fn dice_distribution(d: &Dice, explode_depth: usize) -> Distribution {
+    let die = Distribution::die(d.sides(), d.explode(), explode_depth);
+
+    match d.keep() {
+        None => (0..d.count()).fold(Distribution::constant(0), |acc, _| acc.convolve(&die)),
+        Some(Keep::Highest(k)) => keep(&die, d.count(), k, true),
+        Some(Keep::Lowest(k)) => keep(&die, d.count(), k, false),
+    }
+    .trim()
+}
+
+/// Sum of the `k` highest or lowest of `n` dice
+///
+/// Walks the faces from the kept end, choosing how many of the dice left land on each face,
+/// the first `k` dice met are the kept ones.
+/// So the state is only how many dice left, and how many kept follows from it.
+fn keep(die: &Distribution, n: u32, k: u32, highest: bool) -> Distribution {
+    let (n, k) = (n as usize, k as usize);
+    let len = k * die.max() as usize + 1;
+
+    let ln_fact: Vec<f64> = std::iter::once(0.0)
+        .chain((1..=n).scan(0.0, |acc, i| {
+            *acc += (i as f64).ln();
+            Some(*acc)
+        }))
+        .collect();
+    let ln_choose = |n: usize, c: usize| ln_fact[n] - ln_fact[c] - ln_fact[n - c];
+
+    // totals of the kept dice so far, by how many dice left
+    let mut states: Vec<Option<Vec<f64>>> = vec![None; n + 1];
+    let mut first = vec![0.0; len];
+    first[0] = 1.0;
+    states[n] = Some(first);
+
+    let mut faces: Vec<_> = die.iter().filter(|(_, p)| *p != 0.0).collect();
+    if highest {
+        faces.reverse();
+    }
+
+    for (v, p) in faces {
+        let ln_p = p.ln();
+        let mut next: Vec<Option<Vec<f64>>> = vec![None; n + 1];
+
+        for (rem, state) in states.iter().enumerate() {
+            let state = match state {
+                Some(s) => s,
+                None => continue,
+            };
+            let kept = k.min(n - rem);
+
+            for c in 0..=rem {
+                let weight = (ln_choose(rem, c) + c as f64 * ln_p).exp();
+                if weight == 0.0 {
+                    continue;
+                }
+
+                let shift = v as usize * c.min(k - kept);
+                let to = next[rem - c].get_or_insert_with(|| vec![0.0; len]);
+
+                for (i, s) in state.iter().enumerate().filter(|(_, s)| **s != 0.0) {
+                    to[i + shift] += s * weight;
+                }
+            }
+        }
+
+        states = next;
+    }
+
+    Distribution {
+        min: 0,
+        probs: states[0].take().unwrap_or_else(|| vec![1.0]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dist(s: &str) -> Distribution {
+        crate::parse(s).unwrap().distribution().unwrap()
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    fn assert_close(a: &Distribution, b: &Distribution) {
+        assert_eq!((a.min(), a.max()), (b.min(), b.max()));
+        for ((_, x), (_, y)) in a.iter().zip(b.iter()) {
+            assert!(close(x, y), "{:?} != {:?}", a, b);
+        }
+    }
+
+    /// every ordered outcome of n dice
+    fn brute_keep(die: &Distribution, n: u32, k: usize, highest: bool) -> Distribution {
+        let faces: Vec<_> = die.iter().filter(|(_, p)| *p != 0.0).collect();
+        let mut probs = vec![0.0; k * die.max() as usize + 1];
+
+        for mut i in 0..faces.len().pow(n) {
+            let mut values = Vec::new();
+            let mut p = 1.0;
+            for _ in 0..n {
+                values.push(faces[i % faces.len()].0);
+                p *= faces[i % faces.len()].1;
+                i /= faces.len();
+            }
+
+            values.sort();
+            if highest {
+                values.reverse();
+            }
+            probs[values[..k].iter().sum::<i64>() as usize] += p;
+        }
+
+        Distribution { min: 0, probs }.trim()
+    }
+
+    #[test]
+    fn sum_of_dice() {
+        let d = dist("2d6");
+
+        assert_eq!((d.min(), d.max()), (2, 12));
+        assert!(close(d.probability(7), 6.0 / 36.0));
+        assert!(close(d.mean(), 7.0));
+        assert!(close(d.variance(), 35.0 / 6.0));
+        assert!(close(d.at_least(13), 0.0));
+        assert!(close(d.at_most(2), 1.0 / 36.0));
+        assert!(close(d.iter().map(|(_, p)| p).sum(), 1.0));
+
+        let d = dist("d4-d4+1");
+        assert!(close(d.mean(), 1.0));
+        assert_eq!((d.min(), d.max()), (-2, 4));
+    }
+
+    #[test]
+    fn keep_highest_and_lowest() {
+        let d = dist("d20 adv");
+        assert!(close(d.at_least(20), 1.0 - 0.95 * 0.95));
+        assert!(close(d.mean(), 13.825));
+
+        let d6 = Distribution::die(6, false, 0);
+        assert_close(&dist("4d6kh3"), &brute_keep(&d6, 4, 3, true));
+        assert_close(&dist("5d6kl2"), &brute_keep(&d6, 5, 2, false));
+    }
+
+    #[test]
+    fn explode() {
+        let d = crate::parse("d6!")
+            .unwrap()
+            .distribution_with_depth(1)
+            .unwrap();
+        assert_eq!((d.min(), d.max()), (1, 12));
+        assert!(close(d.probability(5), 1.0 / 6.0));
+        assert!(close(d.probability(6), 0.0));
+        assert!(close(d.probability(12), 1.0 / 36.0));
+        assert!(close(d.mean(), 3.5 + 3.5 / 6.0));
+
+        // with the full depth it's as good as uncapped, 3.5 * 6 / 5
+        assert!(close(dist("d6!").mean(), 4.2));
+
+        let d = crate::parse("3d4!kh2")
+            .unwrap()
+            .distribution_with_depth(2)
+            .unwrap();
+        assert_close(&d, &brute_keep(&Distribution::die(4, true, 2), 3, 2, true));
+    }
+
+    #[test]
+    fn too_large() {
+        assert!(crate::parse("1000d1000000")
+            .unwrap()
+            .distribution()
+            .is_none());
+        assert!(crate::parse("d1000!").unwrap().distribution().is_none());
+        assert!(crate::parse("d1000!")
+            .unwrap()
+            .distribution_with_depth(3)
+            .is_some());
+
+        let big = crate::parse("40d20kh20").unwrap();
+        assert!(big.distribution().is_some());
+        assert!(big.distribution_within(1_000_000).is_none());
+        assert_eq!(
+            crate::parse("2d6").unwrap().distribution_within(1_000),
+            Some(dist("2d6"))
+        );
+    }
+
+    #[test]
+    fn too_large_to_count() {
+        // the work itself overflows u64
+        for s in [
+            "1000d1000000!",
+            "1000d1000000kh999",
+            "1000d1000000kh999+1000d1000000",
+        ] {
+            let expr = crate::parse(s).unwrap();
+
+            assert!(expr.distribution().is_none(), "{}", s);
+            assert!(expr.distribution_within(u64::MAX).is_none(), "{}", s);
+        }
+    }
+}
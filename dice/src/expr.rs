@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// A parsed dice expression, as `4d6kh3 + 2`
+///
+/// Serialized as its canonical notation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct Expr {
+    terms: Vec<(Sign, Term)>,
+}
+
+impl Expr {
+    pub(crate) fn new(terms: Vec<(Sign, Term)>) -> Self {
+        Self { terms }
+    }
+
+    pub fn terms(&self) -> &[(Sign, Term)] {
+        &self.terms
+    }
+}
+
+/// Canonical notation, `d20 adv` is written as `2d20kh1`
+impl Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, (sign, term)) in self.terms.iter().enumerate() {
+            match (i, sign) {
+                (0, Sign::Plus) => {}
+                (0, Sign::Minus) => write!(f, "-")?,
+                (_, Sign::Plus) => write!(f, "+")?,
+                (_, Sign::Minus) => write!(f, "-")?,
+            }
+
+            write!(f, "{}", term)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl From<Expr> for String {
+    fn from(e: Expr) -> Self {
+        e.to_string()
+    }
+}
+
+impl TryFrom<String> for Expr {
+    type Error = crate::parse::error::ParseError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        crate::parse(&s)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Sign {
+    Plus,
+    Minus,
+}
+
+impl Sign {
+    pub fn apply(&self, v: i64) -> i64 {
+        match self {
+            Sign::Plus => v,
+            Sign::Minus => -v,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Term {
+    Dice(Dice),
+    Constant(u32),
+}
+
+impl Display for Term {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Term::Dice(d) => write!(f, "{}", d),
+            Term::Constant(c) => write!(f, "{}", c),
+        }
+    }
+}
+
+/// A group of same dice, as `4d6kh3`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dice {
+    count: u32,
+    sides: u32,
+    keep: Option<Keep>,
+    explode: bool,
+}
+
+impl Dice {
+    pub(crate) fn new(count: u32, sides: u32, keep: Option<Keep>, explode: bool) -> Self {
+        Self {
+            count,
+            sides,
+            keep,
+            explode,
+        }
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    pub fn sides(&self) -> u32 {
+        self.sides
+    }
+
+    pub fn keep(&self) -> Option<Keep> {
+        self.keep
+    }
+
+    /// a die rolling its max rolls again and adds up
+    pub fn explode(&self) -> bool {
+        self.explode
+    }
+
+    /// how many dice count into the total
+    pub fn kept(&self) -> u32 {
+        match self.keep {
+            Some(Keep::Highest(n) | Keep::Lowest(n)) => n,
+            None => self.count,
+        }
+    }
+}
+
+impl Display for Dice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}d{}", self.count, self.sides)?;
+
+        if self.explode {
+            write!(f, "!")?;
+        }
+
+        match self.keep {
+            Some(Keep::Highest(n)) => write!(f, "kh{}", n),
+            Some(Keep::Lowest(n)) => write!(f, "kl{}", n),
+            None => Ok(()),
+        }
+    }
+}
+
+/// which dice of a group count into the total
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keep {
+    Highest(u32),
+    Lowest(u32),
+}
@@ -0,0 +1,23 @@
+/// exact distribution of totals
+pub mod dist;
+/// dice expression syntax tree
+pub mod expr;
+/// text -> expression
+pub mod parse;
+/// dice thrown onto a map
+pub mod physics;
+/// deterministic random numbers for rolling
+pub mod rng;
+/// evaluate an expression into a result
+pub mod roll;
+/// every roll of a session, with who rolled
+pub mod roll_log;
+/// re-run and check shared rolls
+pub mod verify;
+
+pub use dist::Distribution;
+pub use expr::Expr;
+pub use parse::parse;
+pub use rng::DiceRng;
+pub use roll::Roll;
+pub use roll_log::RollLog;
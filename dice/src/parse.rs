@@ -0,0 +1,333 @@
+use self::error::{ParseError, ParseErrorKind, ParseResult};
+use crate::expr::{Dice, Expr, Keep, Sign, Term};
+
+pub mod error;
+
+/// most dice a group could roll
+pub const MAX_DICE: u32 = 1000;
+/// most sides a die could have
+pub const MAX_SIDES: u32 = 1_000_000;
+
+/// Parse a dice expression
+///
+/// - `3d6+2`: dice and constants, added or subtracted
+/// - `d20`: count default as 1
+/// - `d%`: a d100
+/// - `4d6kh3`, `4d6k3`, `2d20kl1`, `4d6dl1`, `4d6dh1`: keep/drop highest/lowest
+/// - `d20 adv`, `d20 dis`: roll twice as many, keep the highest/lowest
+/// - `2d10!`: a die rolling its max rolls again and adds up
+///
+/// Whitespace between terms is ignored, letters are case insensitive.
+///
+/// # Example
+/// ```
+/// let expr = dice::parse("d20 adv + 5").unwrap();
+///
+/// assert_eq!(expr.to_string(), "2d20kh1+5");
+/// ```
+pub fn parse(s: &str) -> ParseResult<Expr> {
+    let mut parser = Parser { s, at: 0 };
+    let mut terms = Vec::new();
+
+    parser.skip_whitespace();
+    if parser.peek().is_none() {
+        return Err(parser.error(ParseErrorKind::Empty));
+    }
+
+    let mut sign = match parser.peek() {
+        Some('-') => {
+            parser.bump();
+            Sign::Minus
+        }
+        Some('+') => {
+            parser.bump();
+            Sign::Plus
+        }
+        _ => Sign::Plus,
+    };
+
+    loop {
+        parser.skip_whitespace();
+        terms.push((sign, parser.term()?));
+        parser.skip_whitespace();
+
+        sign = match parser.peek() {
+            Some('+') => Sign::Plus,
+            Some('-') => Sign::Minus,
+            Some(c) => return Err(parser.error(ParseErrorKind::Unexpected(c))),
+            None => break,
+        };
+        parser.bump();
+    }
+
+    Ok(Expr::new(terms))
+}
+
+struct Parser<'a> {
+    s: &'a str,
+    at: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.s[self.at..].chars().next()
+    }
+
+    fn bump(&mut self) {
+        if let Some(c) = self.peek() {
+            self.at += c.len_utf8();
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    /// eat `word` case insensitively, false and eat nothing if not match
+    fn eat(&mut self, word: &str) -> bool {
+        let rest = &self.s[self.at..];
+
+        // `get` as `rest` may cut a multi-byte char at `word.len()`
+        if rest
+            .get(..word.len())
+            .is_some_and(|r| r.eq_ignore_ascii_case(word))
+        {
+            self.at += word.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        ParseError { at: self.at, kind }
+    }
+
+    fn number(&mut self) -> ParseResult<Option<u32>> {
+        let start = self.at;
+
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.bump();
+        }
+
+        if start == self.at {
+            return Ok(None);
+        }
+
+        self.s[start..self.at]
+            .parse()
+            .map(Some)
+            .map_err(|_| ParseError {
+                at: start,
+                kind: ParseErrorKind::NumberTooLarge,
+            })
+    }
+
+    fn term(&mut self) -> ParseResult<Term> {
+        let start = self.at;
+        let count = self.number()?;
+
+        if !self.eat("d") {
+            return match count {
+                Some(c) => Ok(Term::Constant(c)),
+                None => Err(match self.peek() {
+                    Some(c) => self.error(ParseErrorKind::Unexpected(c)),
+                    None => self.error(ParseErrorKind::UnexpectedEnd),
+                }),
+            };
+        }
+
+        let mut count = count.unwrap_or(1);
+        let sides = if self.eat("%") {
+            100
+        } else {
+            match self.number()? {
+                Some(s) => s,
+                None => {
+                    return Err(match self.peek() {
+                        Some(c) => self.error(ParseErrorKind::Unexpected(c)),
+                        None => self.error(ParseErrorKind::UnexpectedEnd),
+                    })
+                }
+            }
+        };
+
+        if count == 0 || sides == 0 {
+            return Err(ParseError {
+                at: start,
+                kind: ParseErrorKind::Zero,
+            });
+        }
+        if sides > MAX_SIDES {
+            return Err(ParseError {
+                at: start,
+                kind: ParseErrorKind::NumberTooLarge,
+            });
+        }
+        if count > MAX_DICE {
+            return Err(ParseError {
+                at: start,
+                kind: ParseErrorKind::TooManyDice,
+            });
+        }
+
+        let mut explode = false;
+        let mut keep = None;
+
+        loop {
+            let before = self.at;
+            self.skip_whitespace();
+            let modifier_at = self.at;
+
+            let modifier = if self.eat("!") {
+                if sides == 1 {
+                    return Err(self.error(ParseErrorKind::ExplodeD1));
+                }
+                explode = true;
+                continue;
+            } else if self.eat("kh") {
+                Modifier::KeepHighest(self.number()?.unwrap_or(1))
+            } else if self.eat("kl") {
+                Modifier::KeepLowest(self.number()?.unwrap_or(1))
+            } else if self.eat("k") {
+                Modifier::KeepHighest(self.number()?.unwrap_or(1))
+            } else if self.eat("dh") {
+                Modifier::DropHighest(self.number()?.unwrap_or(1))
+            } else if self.eat("dl") {
+                Modifier::DropLowest(self.number()?.unwrap_or(1))
+            } else if self.eat("adv") {
+                Modifier::Advantage
+            } else if self.eat("dis") {
+                Modifier::Disadvantage
+            } else {
+                self.at = before;
+                break;
+            };
+
+            if keep.is_some() {
+                return Err(ParseError {
+                    at: modifier_at,
+                    kind: ParseErrorKind::KeepTwice,
+                });
+            }
+
+            let too_many = |keep| ParseError {
+                at: modifier_at,
+                kind: ParseErrorKind::KeepTooMany { keep, count },
+            };
+
+            keep = Some(match modifier {
+                Modifier::KeepHighest(n) if n > count => return Err(too_many(n)),
+                Modifier::KeepLowest(n) if n > count => return Err(too_many(n)),
+                Modifier::DropHighest(n) if n > count => return Err(too_many(n)),
+                Modifier::DropLowest(n) if n > count => return Err(too_many(n)),
+                Modifier::KeepHighest(n) => Keep::Highest(n),
+                Modifier::KeepLowest(n) => Keep::Lowest(n),
+                Modifier::DropHighest(n) => Keep::Lowest(count - n),
+                Modifier::DropLowest(n) => Keep::Highest(count - n),
+                Modifier::Advantage => {
+                    count *= 2;
+                    Keep::Highest(count / 2)
+                }
+                Modifier::Disadvantage => {
+                    count *= 2;
+                    Keep::Lowest(count / 2)
+                }
+            });
+        }
+
+        if count > MAX_DICE {
+            return Err(ParseError {
+                at: start,
+                kind: ParseErrorKind::TooManyDice,
+            });
+        }
+
+        Ok(Term::Dice(Dice::new(count, sides, keep, explode)))
+    }
+}
+
+enum Modifier {
+    KeepHighest(u32),
+    KeepLowest(u32),
+    DropHighest(u32),
+    DropLowest(u32),
+    Advantage,
+    Disadvantage,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(s: &str) -> String {
+        parse(s).unwrap().to_string()
+    }
+
+    fn error(s: &str) -> ParseErrorKind {
+        parse(s).unwrap_err().kind
+    }
+
+    #[test]
+    fn parse_notation() {
+        assert_eq!(canonical("3d6+2"), "3d6+2");
+        assert_eq!(canonical(" 3D6 + 2 "), "3d6+2");
+        assert_eq!(canonical("d20"), "1d20");
+        assert_eq!(canonical("d%"), "1d100");
+        assert_eq!(canonical("4d6kh3"), "4d6kh3");
+        assert_eq!(canonical("4d6k3"), "4d6kh3");
+        assert_eq!(canonical("4d6dl1"), "4d6kh3");
+        assert_eq!(canonical("4d6dh1"), "4d6kl3");
+        assert_eq!(canonical("2d20kl"), "2d20kl1");
+        assert_eq!(canonical("d20 adv"), "2d20kh1");
+        assert_eq!(canonical("d20 dis - 1"), "2d20kl1-1");
+        assert_eq!(canonical("2d10!"), "2d10!");
+        assert_eq!(canonical("-d4+1d8-3"), "-1d4+1d8-3");
+        assert_eq!(canonical("7"), "7");
+    }
+
+    #[test]
+    fn parse_terms() {
+        let expr = parse("2d10!+5").unwrap();
+
+        assert_eq!(
+            expr.terms(),
+            &[
+                (Sign::Plus, Term::Dice(Dice::new(2, 10, None, true))),
+                (Sign::Plus, Term::Constant(5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(error(""), ParseErrorKind::Empty);
+        assert_eq!(error("   "), ParseErrorKind::Empty);
+        assert_eq!(error("3d"), ParseErrorKind::UnexpectedEnd);
+        assert_eq!(error("3d6+"), ParseErrorKind::UnexpectedEnd);
+        assert_eq!(error("3x6"), ParseErrorKind::Unexpected('x'));
+        assert_eq!(error("3d6 2"), ParseErrorKind::Unexpected('2'));
+        assert_eq!(error("0d6"), ParseErrorKind::Zero);
+        assert_eq!(error("2d0"), ParseErrorKind::Zero);
+        assert_eq!(error("1d1!"), ParseErrorKind::ExplodeD1);
+        assert_eq!(
+            error("2d6kh3"),
+            ParseErrorKind::KeepTooMany { keep: 3, count: 2 }
+        );
+        assert_eq!(error("d20 adv kh1"), ParseErrorKind::KeepTwice);
+        assert_eq!(error("5000d6"), ParseErrorKind::TooManyDice);
+        assert_eq!(error("99999999999d6"), ParseErrorKind::NumberTooLarge);
+        assert_eq!(parse("3d6 + x").unwrap_err().at(), 6);
+    }
+
+    #[test]
+    fn parse_non_ascii() {
+        assert_eq!(error("1dé"), ParseErrorKind::Unexpected('é'));
+        assert_eq!(error("2d6ü"), ParseErrorKind::Unexpected('ü'));
+        assert_eq!(error("2d6 é"), ParseErrorKind::Unexpected('é'));
+        assert_eq!(error("ド"), ParseErrorKind::Unexpected('ド'));
+        assert_eq!(error("d20 advä"), ParseErrorKind::Unexpected('ä'));
+    }
+}
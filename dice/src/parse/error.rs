@@ -0,0 +1,63 @@
+use std::fmt::Display;
+
+pub type ParseResult<T> = Result<T, ParseError>;
+
+/// Why an expression couldn't be parsed, and where
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub(super) at: usize,
+    pub(super) kind: ParseErrorKind,
+}
+
+impl ParseError {
+    /// byte offset into the expression
+    pub fn at(&self) -> usize {
+        self.at
+    }
+
+    pub fn kind(&self) -> &ParseErrorKind {
+        &self.kind
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    Empty,
+    Unexpected(char),
+    UnexpectedEnd,
+    NumberTooLarge,
+    /// `0d6` or `d0`
+    Zero,
+    /// keep more dice than rolled
+    KeepTooMany {
+        keep: u32,
+        count: u32,
+    },
+    /// two of `kh`, `kl`, `dh`, `dl`, `adv`, `dis` on one group
+    KeepTwice,
+    /// a d1 would explode forever
+    ExplodeD1,
+    TooManyDice,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let msg = match &self.kind {
+            ParseErrorKind::Empty => "empty expression".to_owned(),
+            ParseErrorKind::Unexpected(c) => format!("unexpected [{}]", c),
+            ParseErrorKind::UnexpectedEnd => "unexpected end".to_owned(),
+            ParseErrorKind::NumberTooLarge => "number too large".to_owned(),
+            ParseErrorKind::Zero => "dice count and sides must not be zero".to_owned(),
+            ParseErrorKind::KeepTooMany { keep, count } => {
+                format!("keep [{}] dice of [{}]", keep, count)
+            }
+            ParseErrorKind::KeepTwice => "more than one keep/drop on a dice group".to_owned(),
+            ParseErrorKind::ExplodeD1 => "a d1 can't explode".to_owned(),
+            ParseErrorKind::TooManyDice => "too many dice".to_owned(),
+        };
+
+        write!(f, "ParseError-> at [{}]-> {}", self.at, msg)
+    }
+}
+
+impl std::error::Error for ParseError {}
@@ -0,0 +1,669 @@
+use crate::{rng::DiceRng, verify::Reveal, Expr, Roll};
+use cgmath::{InnerSpace, Point3, Quaternion, Rotation, Vector3, Zero};
+use scenes::OcTree;
+use serde::{Deserialize, Serialize};
+use shape::Shape;
+
+pub mod shape;
+
+/// seconds of a step
+pub const STEP: f64 = 1.0 / 120.0;
+/// a throw still moving after it is read where it is
+pub const MAX_STEPS: usize = 120 * 20;
+/// voxels per second squared, toward -z
+pub const GRAVITY: f64 = 20.0;
+/// distance from the centre to the farthest corner of a die, in voxels
+pub const DIE_RADIUS: f64 = 0.25;
+/// dice of a roll thrown onto the map, the rest are rolled by the seed,
+/// or a roll as 1000d20 keeps the page busy for ages
+pub const MAX_THROWN: usize = 32;
+
+/// bounciness
+const RESTITUTION: f64 = 0.3;
+/// contacts slower than this don't bounce, or a die never rests
+const BOUNCE_THRESHOLD: f64 = 1.0;
+const FRICTION: f64 = 0.5;
+/// passes over the contacts in a step
+const SOLVER_ITERATIONS: usize = 8;
+/// corners this close above a floor count as touching it
+const CONTACT_SLOP: f64 = 0.005;
+/// corners this deep in a voxel are left there, or a resting die jitters
+const PENETRATION_SLOP: f64 = 0.005;
+/// part of the penetration pushed out each step
+const CORRECTION: f64 = 0.2;
+const LINEAR_DAMPING: f64 = 0.999;
+const ANGULAR_DAMPING: f64 = 0.99;
+/// below both speeds for this many steps, a die is at rest
+const REST_SPEED: f64 = 0.05;
+const REST_SPIN: f64 = 0.1;
+const REST_STEPS: usize = 30;
+
+/// Where and how a die leaves the hand
+///
+/// All of a throw is plain arithmetic in f64,
+/// so the same throw lands the same on every client.
+/// Dice of a roll are thrown one by one, they don't hit each other.
+///
+/// # Example
+/// ```
+/// # use cgmath::Point3;
+/// # use dice::physics::{shape::Shape, Throw};
+/// # use scenes::{Instance, OcTree};
+/// let mut floor = OcTree::from_scope(8);
+/// for x in -4..4 {
+///     for y in -4..4 {
+///         floor.insert(Instance::new(Point3::new(x, y, -1), "stone"));
+///     }
+/// }
+///
+/// let throw = Throw::from_seed(Shape::D20, Point3::new(0.5, 0.5, 2.0), 42);
+/// let landing = throw.simulate(&floor);
+///
+/// assert!((1..=20).contains(&landing.value().unwrap()));
+/// assert_eq!(landing, throw.simulate(&floor));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Throw {
+    shape: Shape,
+    position: Point3<f64>,
+    orientation: Quaternion<f64>,
+    velocity: Vector3<f64>,
+    spin: Vector3<f64>,
+}
+
+impl Throw {
+    pub fn new(
+        shape: Shape,
+        position: Point3<f64>,
+        orientation: Quaternion<f64>,
+        velocity: Vector3<f64>,
+        spin: Vector3<f64>,
+    ) -> Self {
+        Self {
+            shape,
+            position,
+            orientation: orientation.normalize(),
+            velocity,
+            spin,
+        }
+    }
+
+    /// from a point, with orientation, direction and spin picked by the seed
+    pub fn from_seed(shape: Shape, position: Point3<f64>, seed: u64) -> Self {
+        let mut rng = DiceRng::seed_from_u64(seed);
+        let mut unit = || rng.next_u64() as f64 / u64::MAX as f64 * 2.0 - 1.0;
+
+        // uniform random rotation by rejection, no trigonometry
+        let orientation = loop {
+            let q = Quaternion::new(unit(), unit(), unit(), unit());
+            let m = q.magnitude2();
+            if m > 1e-6 && m <= 1.0 {
+                break q;
+            }
+        };
+        let velocity = Vector3::new(unit() * 3.0, unit() * 3.0, unit() * 2.0);
+        let spin = Vector3::new(unit(), unit(), unit()) * 20.0;
+
+        Self::new(shape, position, orientation, velocity, spin)
+    }
+
+    pub fn shape(&self) -> Shape {
+        self.shape
+    }
+
+    pub fn position(&self) -> Point3<f64> {
+        self.position
+    }
+
+    /// run the throw until the die rests, or for [`MAX_STEPS`]
+    pub fn simulate(&self, map: &OcTree) -> Landing {
+        let mut sim = Simulation::new(*self, map);
+        let mut poses = vec![sim.pose()];
+
+        while !sim.at_rest() && !sim.fell() && sim.steps() < MAX_STEPS {
+            sim.step();
+            poses.push(sim.pose());
+        }
+
+        Landing {
+            value: sim.value(),
+            at_rest: sim.at_rest(),
+            poses,
+        }
+    }
+}
+
+impl Expr {
+    /// Throw the dice onto the map from `at`, one by one, with a seed
+    ///
+    /// Each die of a [`Shape`] is thrown as the seed picks,
+    /// the face it rests on is its roll.
+    /// Dice of no shape, like d100, dice falling off the map
+    /// and dice past the first [`MAX_THROWN`] are rolled by the seed instead.
+    /// Any client with the same map re-runs it with [`Roll::verify_on`].
+    ///
+    /// # Example
+    /// ```
+    /// # use cgmath::Point3;
+    /// # use scenes::{Instance, OcTree};
+    /// let mut floor = OcTree::from_scope(8);
+    /// for x in -4..4 {
+    ///     for y in -4..4 {
+    ///         floor.insert(Instance::new(Point3::new(x, y, -1), "stone"));
+    ///     }
+    /// }
+    ///
+    /// let expr = dice::parse("2d6+1").unwrap();
+    /// let roll = expr.throw(42, &floor, Point3::new(0.5, 0.5, 2.0));
+    ///
+    /// assert!((3..=13).contains(&roll.total()));
+    /// assert!(roll.verify_on(&floor).is_ok());
+    /// ```
+    pub fn throw(&self, seed: u64, map: &OcTree, at: Point3<f64>) -> Roll {
+        let mut thrown = 0;
+
+        self.roll_with(seed, Some(at), |sides, rng| {
+            let throw_seed = rng.next_u64();
+
+            Shape::from_sides(sides)
+                .filter(|_| thrown < MAX_THROWN)
+                .and_then(|shape| {
+                    thrown += 1;
+
+                    Throw::from_seed(shape, at, throw_seed)
+                        .simulate(map)
+                        .value()
+                })
+                .unwrap_or_else(|| rng.roll_die(sides))
+        })
+    }
+
+    /// throw with the seed derived from a committed secret
+    /// and the salt other players contributed, see [`crate::verify`]
+    pub fn reveal_thrown(
+        &self,
+        secret: [u8; 32],
+        salt: Vec<u8>,
+        map: &OcTree,
+        at: Point3<f64>,
+    ) -> Roll {
+        self.reveal_with(Reveal::new(secret, salt), |seed| self.throw(seed, map, at))
+    }
+}
+
+/// Place of a die at one step
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Pose {
+    position: Point3<f64>,
+    orientation: Quaternion<f64>,
+}
+
+impl Pose {
+    pub fn position(&self) -> Point3<f64> {
+        self.position
+    }
+
+    pub fn orientation(&self) -> Quaternion<f64> {
+        self.orientation
+    }
+}
+
+/// How a throw ended, with every step of it to replay
+#[derive(Debug, Clone, PartialEq)]
+pub struct Landing {
+    value: Option<u32>,
+    at_rest: bool,
+    poses: Vec<Pose>,
+}
+
+impl Landing {
+    /// the face up, None if the die fell off the map
+    pub fn value(&self) -> Option<u32> {
+        self.value
+    }
+
+    /// false if it was still moving after [`MAX_STEPS`]
+    pub fn at_rest(&self) -> bool {
+        self.at_rest
+    }
+
+    /// one for the throw and one after each step, [`STEP`] apart
+    pub fn poses(&self) -> &[Pose] {
+        &self.poses
+    }
+}
+
+/// A die in flight over a map, stepped by hand to animate it
+#[derive(Debug, Clone)]
+pub struct Simulation<'a> {
+    map: &'a OcTree,
+    shape: Shape,
+    vertices: Vec<Vector3<f64>>,
+    position: Point3<f64>,
+    orientation: Quaternion<f64>,
+    velocity: Vector3<f64>,
+    spin: Vector3<f64>,
+    steps: usize,
+    still_steps: usize,
+}
+
+impl<'a> Simulation<'a> {
+    pub fn new(throw: Throw, map: &'a OcTree) -> Self {
+        Self {
+            map,
+            shape: throw.shape,
+            vertices: throw
+                .shape
+                .vertices()
+                .into_iter()
+                .map(|v| v * DIE_RADIUS)
+                .collect(),
+            position: throw.position,
+            orientation: throw.orientation,
+            velocity: throw.velocity,
+            spin: throw.spin,
+            steps: 0,
+            still_steps: 0,
+        }
+    }
+
+    pub fn pose(&self) -> Pose {
+        Pose {
+            position: self.position,
+            orientation: self.orientation,
+        }
+    }
+
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn at_rest(&self) -> bool {
+        self.still_steps >= REST_STEPS
+    }
+
+    /// below every voxel the map could hold
+    pub fn fell(&self) -> bool {
+        let bottom = self.map.central().z as f64 - self.map.scope() as f64;
+
+        self.position.z < bottom - 1.0
+    }
+
+    /// the face pointing up now
+    pub fn value(&self) -> Option<u32> {
+        if self.fell() {
+            return None;
+        }
+
+        self.shape
+            .faces()
+            .into_iter()
+            .map(|(dir, value)| (self.orientation.rotate_vector(dir).z, value))
+            .fold(None, |best: Option<(f64, u32)>, face| match best {
+                Some(b) if b.0 >= face.0 => Some(b),
+                _ => Some(face),
+            })
+            .map(|(_, value)| value)
+    }
+
+    /// advance by [`STEP`]
+    pub fn step(&mut self) {
+        self.velocity.z -= GRAVITY * STEP;
+
+        let mut contacts: Vec<_> = self
+            .contacts()
+            .into_iter()
+            .map(|c| Solving::new(c, self.point_velocity(c.arm)))
+            .collect();
+        for _ in 0..SOLVER_ITERATIONS {
+            for c in &mut contacts {
+                self.resolve(c);
+            }
+        }
+
+        self.velocity *= LINEAR_DAMPING;
+        self.spin *= ANGULAR_DAMPING;
+
+        self.position += self.velocity * STEP;
+        let w = Quaternion::from_sv(0.0, self.spin);
+        self.orientation = (self.orientation + w * self.orientation * (0.5 * STEP)).normalize();
+
+        if !contacts.is_empty()
+            && self.velocity.magnitude() < REST_SPEED
+            && self.spin.magnitude() < REST_SPIN
+        {
+            self.still_steps += 1;
+        } else {
+            self.still_steps = 0;
+        }
+
+        self.steps += 1;
+    }
+
+    /// corners inside solid voxels
+    fn contacts(&self) -> Vec<Contact> {
+        self.vertices
+            .iter()
+            .filter_map(|v| {
+                let arm = self.orientation.rotate_vector(*v);
+                let p = self.position + arm;
+                let cell = Point3::new(p.x.floor(), p.y.floor(), p.z.floor());
+
+                if !self.solid(cell, Vector3::zero()) {
+                    // resting corners hovering just above a floor still hold the die up
+                    let below = p.z - CONTACT_SLOP;
+                    let under = Point3::new(cell.x, cell.y, below.floor());
+
+                    return (under.z < cell.z && self.solid(under, Vector3::zero())).then(|| {
+                        Contact {
+                            arm,
+                            normal: Vector3::unit_z(),
+                            depth: cell.z - p.z,
+                        }
+                    });
+                }
+
+                // out through the nearest face with nothing behind it, or up if buried
+                let f = p - cell;
+                let candidates = [
+                    (Vector3::unit_z(), 1.0 - f.z),
+                    (-Vector3::unit_z(), f.z),
+                    (Vector3::unit_x(), 1.0 - f.x),
+                    (-Vector3::unit_x(), f.x),
+                    (Vector3::unit_y(), 1.0 - f.y),
+                    (-Vector3::unit_y(), f.y),
+                ];
+                let (normal, depth) = candidates
+                    .into_iter()
+                    .filter(|(n, _)| !self.solid(cell, *n))
+                    .fold(None, |best: Option<(Vector3<f64>, f64)>, c| match best {
+                        Some(b) if b.1 <= c.1 => Some(b),
+                        _ => Some(c),
+                    })
+                    .unwrap_or(candidates[0]);
+
+                Some(Contact { arm, normal, depth })
+            })
+            .collect()
+    }
+
+    fn solid(&self, cell: Point3<f64>, offset: Vector3<f64>) -> bool {
+        let p = cell + offset;
+
+        self.map
+            .get(Point3::new(p.x as i32, p.y as i32, p.z as i32))
+            .is_some()
+    }
+
+    fn point_velocity(&self, arm: Vector3<f64>) -> Vector3<f64> {
+        self.velocity + self.spin.cross(arm)
+    }
+
+    fn apply(&mut self, arm: Vector3<f64>, impulse: Vector3<f64>) {
+        self.velocity += impulse * INV_MASS;
+        self.spin += arm.cross(impulse) * INV_INERTIA;
+    }
+
+    /// One pass of sequential impulses on a contact
+    ///
+    /// Impulses are accumulated over the passes and clamped as a whole,
+    /// so the contacts settle on a balance instead of fighting each other.
+    fn resolve(&mut self, s: &mut Solving) {
+        let c = s.contact;
+
+        let vn = self.point_velocity(c.arm).dot(c.normal);
+        let total = (s.pushed + (s.target - vn) / effective_mass(c.arm, c.normal)).max(0.0);
+        self.apply(c.arm, c.normal * (total - s.pushed));
+        s.pushed = total;
+
+        let limit = FRICTION * s.pushed;
+        for (t, rubbed) in s.tangents.iter().zip(s.rubbed.iter_mut()) {
+            let vt = self.point_velocity(c.arm).dot(*t);
+            let total = (*rubbed - vt / effective_mass(c.arm, *t)).clamp(-limit, limit);
+            self.apply(c.arm, *t * (total - *rubbed));
+            *rubbed = total;
+        }
+    }
+}
+
+const INV_MASS: f64 = 1.0;
+/// as a solid ball, every die spins about the same whichever the axis
+const INV_INERTIA: f64 = 1.0 / (0.4 * DIE_RADIUS * DIE_RADIUS);
+
+/// inverse of the mass the corner pushes, along a direction
+fn effective_mass(arm: Vector3<f64>, dir: Vector3<f64>) -> f64 {
+    INV_MASS + INV_INERTIA * arm.cross(dir).magnitude2()
+}
+
+/// A contact with the impulses given to it so far this step
+#[derive(Debug, Clone, Copy)]
+struct Solving {
+    contact: Contact,
+    /// normal speed the corner should leave with
+    target: f64,
+    tangents: [Vector3<f64>; 2],
+    pushed: f64,
+    rubbed: [f64; 2],
+}
+
+impl Solving {
+    fn new(contact: Contact, v: Vector3<f64>) -> Self {
+        let n = contact.normal;
+        let vn = v.dot(n);
+
+        let target = if contact.depth < 0.0 {
+            // not touching yet, it may close the gap
+            contact.depth / STEP
+        } else if -vn > BOUNCE_THRESHOLD {
+            -vn * RESTITUTION
+        } else {
+            // push out of the voxel a part at a time
+            (contact.depth - PENETRATION_SLOP).max(0.0) * CORRECTION / STEP
+        };
+
+        let side = if n.x.abs() < 0.9 {
+            Vector3::unit_x()
+        } else {
+            Vector3::unit_y()
+        };
+        let t = side.cross(n).normalize();
+
+        Self {
+            contact,
+            target,
+            tangents: [t, n.cross(t)],
+            pushed: 0.0,
+            rubbed: [0.0; 2],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Contact {
+    /// from the centre of the die to the corner
+    arm: Vector3<f64>,
+    /// out of the voxel
+    normal: Vector3<f64>,
+    depth: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scenes::Instance;
+
+    const SHAPES: [Shape; 6] = [
+        Shape::D4,
+        Shape::D6,
+        Shape::D8,
+        Shape::D10,
+        Shape::D12,
+        Shape::D20,
+    ];
+
+    fn floor() -> OcTree {
+        let mut ans = OcTree::from_scope(8);
+        for x in -6..6 {
+            for y in -6..6 {
+                ans.insert(Instance::new(Point3::new(x, y, -1), "stone"));
+            }
+        }
+        ans
+    }
+
+    #[test]
+    fn same_throw_same_landing() {
+        let floor = floor();
+
+        for shape in SHAPES {
+            let throw = Throw::from_seed(shape, Point3::new(0.5, 0.5, 2.0), 7);
+
+            assert_eq!(throw.simulate(&floor), throw.simulate(&floor));
+            assert_ne!(
+                throw.simulate(&floor).poses(),
+                Throw::from_seed(shape, Point3::new(0.5, 0.5, 2.0), 8)
+                    .simulate(&floor)
+                    .poses()
+            );
+        }
+    }
+
+    #[test]
+    fn rest_flat_on_the_floor() {
+        let floor = floor();
+
+        for shape in SHAPES {
+            let mut values = Vec::new();
+
+            for seed in 0..20 {
+                let landing =
+                    Throw::from_seed(shape, Point3::new(0.5, 0.5, 2.0), seed).simulate(&floor);
+                let pose = landing.poses().last().unwrap();
+                let up = shape
+                    .faces()
+                    .into_iter()
+                    .find(|(_, v)| Some(*v) == landing.value())
+                    .map(|(dir, _)| pose.orientation().rotate_vector(dir))
+                    .unwrap();
+
+                assert!(landing.at_rest(), "{:?} {} {:?}", shape, seed, pose);
+                assert!(up.z > 0.99, "{:?} {} cocked {:?}", shape, seed, up);
+                assert!(pose.position().z > 0.0 && pose.position().z < DIE_RADIUS);
+                values.push(landing.value().unwrap());
+            }
+
+            values.sort();
+            values.dedup();
+            assert!(values.len() > 2, "{:?} {:?}", shape, values);
+        }
+    }
+
+    #[test]
+    fn stopped_by_a_wall() {
+        let mut map = floor();
+        for y in -6..6 {
+            for z in 0..3 {
+                map.insert(Instance::new(Point3::new(2, y, z), "stone"));
+            }
+        }
+
+        let throw = Throw::new(
+            Shape::D6,
+            Point3::new(0.5, 0.5, 0.5),
+            Quaternion::new(1.0, 0.0, 0.0, 0.0),
+            Vector3::new(10.0, 0.0, 0.0),
+            Vector3::zero(),
+        );
+        let landing = throw.simulate(&map);
+
+        assert!(landing.poses().iter().all(|p| p.position().x < 2.0));
+        assert!(landing.at_rest());
+    }
+
+    #[test]
+    fn resting_faces_decide_the_roll() {
+        let floor = floor();
+        let at = Point3::new(0.5, 0.5, 2.0);
+        let expr = crate::parse("2d6+d20+d3").unwrap();
+
+        let roll = expr.throw(5, &floor, at);
+
+        // every die of a shape is its own throw, seeded in turn
+        let mut rng = DiceRng::seed_from_u64(5);
+        let thrown: Vec<_> = [Shape::D6, Shape::D6, Shape::D20]
+            .into_iter()
+            .map(|shape| {
+                let landing = Throw::from_seed(shape, at, rng.next_u64()).simulate(&floor);
+                landing.value().unwrap()
+            })
+            .collect();
+        let dice: Vec<_> = roll.terms().iter().flat_map(|t| t.dice()).collect();
+        assert_eq!(
+            dice[..3].iter().map(|d| d.value()).collect::<Vec<_>>(),
+            thrown
+        );
+        assert!((1..=3).contains(&dice[3].value()));
+
+        assert_eq!(roll.thrown(), Some(at));
+        assert_eq!(roll.verify(), Err(crate::verify::VerifyError::Thrown));
+        assert_eq!(roll.verify_on(&floor), Ok(()));
+        assert_ne!(roll.hash(), expr.roll(5).hash());
+    }
+
+    #[test]
+    fn throw_at_most_max_thrown() {
+        let floor = floor();
+        let at = Point3::new(0.5, 0.5, 2.0);
+        let expr = crate::parse("1000d20").unwrap();
+
+        let roll = expr.throw(9, &floor, at);
+
+        // the first dice are thrown, the rest rolled by the seed after them
+        let mut rng = DiceRng::seed_from_u64(9);
+        let expect: Vec<_> = (0..1000)
+            .map(|i| {
+                let throw_seed = rng.next_u64();
+
+                if i < MAX_THROWN {
+                    Throw::from_seed(Shape::D20, at, throw_seed)
+                        .simulate(&floor)
+                        .value()
+                        .unwrap()
+                } else {
+                    rng.roll_die(20)
+                }
+            })
+            .collect();
+        let dice: Vec<_> = roll.terms()[0].dice().iter().map(|d| d.value()).collect();
+
+        assert_eq!(dice, expect);
+        assert_eq!(roll.verify_on(&floor), Ok(()));
+    }
+
+    #[test]
+    fn thrown_off_the_map() {
+        let at = Point3::new(0.5, 0.5, 2.0);
+        let expr = crate::parse("4d6").unwrap();
+        let nowhere = OcTree::from_scope(4);
+
+        let roll = expr.reveal_thrown([1; 32], b"salt".to_vec(), &nowhere, at);
+
+        assert!((4..=24).contains(&roll.total()));
+        assert_eq!(roll.verify_on(&nowhere), Ok(()));
+        assert!(crate::verify::Commitment::new(&[1; 32]).opens(&roll));
+        // the dice on a floor land otherwise
+        assert_eq!(
+            roll.verify_on(&floor()),
+            Err(crate::verify::VerifyError::ResultDismatch)
+        );
+    }
+
+    #[test]
+    fn fall_off_the_map() {
+        let throw = Throw::from_seed(Shape::D6, Point3::new(0.5, 0.5, 2.0), 1);
+        let landing = throw.simulate(&OcTree::from_scope(4));
+
+        assert_eq!(landing.value(), None);
+        assert!(!landing.at_rest());
+    }
+}
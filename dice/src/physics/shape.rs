@@ -0,0 +1,266 @@
+use cgmath::{InnerSpace, Vector3};
+use serde::{Deserialize, Serialize};
+
+/// Polyhedra a die could be
+///
+/// Geometry is built with arithmetic and `sqrt` only,
+/// which give the same bits on every platform, unlike `sin` and `cos`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Shape {
+    D4,
+    D6,
+    D8,
+    D10,
+    D12,
+    D20,
+}
+
+impl Shape {
+    /// None if no such physical die
+    pub fn from_sides(sides: u32) -> Option<Self> {
+        match sides {
+            4 => Some(Self::D4),
+            6 => Some(Self::D6),
+            8 => Some(Self::D8),
+            10 => Some(Self::D10),
+            12 => Some(Self::D12),
+            20 => Some(Self::D20),
+            _ => None,
+        }
+    }
+
+    pub fn sides(&self) -> u32 {
+        match self {
+            Self::D4 => 4,
+            Self::D6 => 6,
+            Self::D8 => 8,
+            Self::D10 => 10,
+            Self::D12 => 12,
+            Self::D20 => 20,
+        }
+    }
+
+    /// corners in body space, the farthest at distance 1
+    pub fn vertices(&self) -> Vec<Vector3<f64>> {
+        let ans = match self {
+            Self::D4 => tetrahedron(),
+            Self::D6 => cube(),
+            Self::D8 => octahedron(),
+            Self::D10 => trapezohedron(),
+            Self::D12 => dodecahedron(),
+            Self::D20 => icosahedron(),
+        };
+
+        let r = ans.iter().map(|v| v.magnitude()).fold(0.0, f64::max);
+
+        ans.into_iter().map(|v| v / r).collect()
+    }
+
+    /// Unit directions in body space with their values,
+    /// the one pointing up at rest reads the die
+    ///
+    /// Face normals, but corners for a d4, which is read by its top corner.
+    /// Opposite faces add up to `sides + 1`.
+    pub fn faces(&self) -> Vec<(Vector3<f64>, u32)> {
+        let dirs: Vec<_> = match self {
+            Self::D4 => tetrahedron(),
+            Self::D6 => octahedron(),
+            Self::D8 => cube(),
+            Self::D10 => trapezohedron_normals(),
+            Self::D12 => icosahedron(),
+            Self::D20 => dodecahedron(),
+        }
+        .into_iter()
+        .map(InnerSpace::normalize)
+        .collect();
+
+        if let Self::D4 = self {
+            return dirs.into_iter().zip(1..).collect();
+        }
+
+        let mut values = vec![0; dirs.len()];
+        let mut next = 1;
+
+        for i in 0..dirs.len() {
+            if values[i] != 0 {
+                continue;
+            }
+
+            let opposite = (0..dirs.len())
+                .find(|j| (dirs[i] + dirs[*j]).magnitude2() < 1e-9)
+                .expect("faces of a die come in opposite pairs");
+
+            values[i] = next;
+            values[opposite] = self.sides() + 1 - next;
+            next += 1;
+        }
+
+        dirs.into_iter().zip(values).collect()
+    }
+}
+
+fn golden() -> f64 {
+    (1.0 + 5f64.sqrt()) / 2.0
+}
+
+/// every sign combination of the non-zero components
+fn signs(v: [f64; 3]) -> Vec<Vector3<f64>> {
+    let mut ans = vec![Vector3::new(v[0], v[1], v[2])];
+
+    for axis in 0..3 {
+        if v[axis] != 0.0 {
+            ans = ans
+                .into_iter()
+                .flat_map(|p| {
+                    let mut n = p;
+                    n[axis] = -n[axis];
+                    [p, n]
+                })
+                .collect();
+        }
+    }
+
+    ans
+}
+
+fn tetrahedron() -> Vec<Vector3<f64>> {
+    vec![
+        Vector3::new(1.0, 1.0, 1.0),
+        Vector3::new(1.0, -1.0, -1.0),
+        Vector3::new(-1.0, 1.0, -1.0),
+        Vector3::new(-1.0, -1.0, 1.0),
+    ]
+}
+
+fn cube() -> Vec<Vector3<f64>> {
+    signs([1.0, 1.0, 1.0])
+}
+
+fn octahedron() -> Vec<Vector3<f64>> {
+    [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]
+        .into_iter()
+        .flat_map(signs)
+        .collect()
+}
+
+fn icosahedron() -> Vec<Vector3<f64>> {
+    let g = golden();
+
+    [[0.0, 1.0, g], [1.0, g, 0.0], [g, 0.0, 1.0]]
+        .into_iter()
+        .flat_map(signs)
+        .collect()
+}
+
+/// dual of [`icosahedron`], corners of one are face normals of the other
+fn dodecahedron() -> Vec<Vector3<f64>> {
+    let g = golden();
+
+    [
+        [1.0, 1.0, 1.0],
+        [1.0 / g, 0.0, g],
+        [0.0, g, 1.0 / g],
+        [g, 1.0 / g, 0.0],
+    ]
+    .into_iter()
+    .flat_map(signs)
+    .collect()
+}
+
+/// (cos, sin) of every multiple of 36°
+fn tenths() -> Vec<(f64, f64)> {
+    let c = golden() / 2.0;
+    let s = (1.0 - c * c).sqrt();
+
+    std::iter::successors(Some((1.0, 0.0)), |(x, y)| {
+        Some((x * c - y * s, x * s + y * c))
+    })
+    .take(10)
+    .collect()
+}
+
+/// height of the ring corners of a trapezohedron with poles at height 1,
+/// so the kites are flat
+fn ring_height() -> f64 {
+    let c = golden() / 2.0;
+
+    (1.0 - c) / (1.0 + c)
+}
+
+/// d10, poles up and down, a zigzag ring of ten around
+fn trapezohedron() -> Vec<Vector3<f64>> {
+    let h = ring_height();
+    let ring = tenths().into_iter().enumerate().map(|(k, (x, y))| {
+        let z = if k & 1 == 0 { h } else { -h };
+        Vector3::new(x, y, z)
+    });
+
+    [Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0)]
+        .into_iter()
+        .chain(ring)
+        .collect()
+}
+
+/// upper kites face the odd tenths, lower kites the even ones
+fn trapezohedron_normals() -> Vec<Vector3<f64>> {
+    let r = 1.0 + ring_height();
+
+    tenths()
+        .into_iter()
+        .enumerate()
+        .map(|(k, (x, y))| {
+            let z = if k & 1 == 1 { 1.0 } else { -1.0 };
+            Vector3::new(x * r, y * r, z)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHAPES: [Shape; 6] = [
+        Shape::D4,
+        Shape::D6,
+        Shape::D8,
+        Shape::D10,
+        Shape::D12,
+        Shape::D20,
+    ];
+
+    #[test]
+    fn faces_numbered() {
+        for shape in SHAPES {
+            let faces = shape.faces();
+            let mut values: Vec<_> = faces.iter().map(|(_, v)| *v).collect();
+            values.sort();
+
+            assert_eq!(values, (1..=shape.sides()).collect::<Vec<_>>());
+            assert_eq!(Shape::from_sides(shape.sides()), Some(shape));
+        }
+    }
+
+    /// every face normal is perpendicular to the corners of its face
+    #[test]
+    fn faces_flat() {
+        for shape in SHAPES {
+            let vertices = shape.vertices();
+
+            for (n, _) in shape.faces() {
+                let top = vertices.iter().map(|v| v.dot(n)).fold(f64::MIN, f64::max);
+                let on_face = vertices
+                    .iter()
+                    .filter(|v| (v.dot(n) - top).abs() < 1e-9)
+                    .count();
+
+                let expected = match shape {
+                    Shape::D4 => 1,
+                    Shape::D6 | Shape::D10 => 4,
+                    Shape::D8 | Shape::D20 => 3,
+                    Shape::D12 => 5,
+                };
+                assert_eq!(on_face, expected, "{:?}", shape);
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+/// xoshiro256** seeded by splitmix64
+///
+/// Implemented here rather than borrowed,
+/// so the same seed rolls the same dice on every platform and every version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiceRng {
+    s: [u64; 4],
+}
+
+impl DiceRng {
+    pub fn seed_from_u64(seed: u64) -> Self {
+        let mut sm = seed;
+        let mut next = || {
+            sm = sm.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = sm;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        };
+
+        Self {
+            s: [next(), next(), next(), next()],
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let ans = self.s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = self.s[1] << 17;
+
+        self.s[2] ^= self.s[0];
+        self.s[3] ^= self.s[1];
+        self.s[1] ^= self.s[2];
+        self.s[0] ^= self.s[3];
+        self.s[2] ^= t;
+        self.s[3] = self.s[3].rotate_left(45);
+
+        ans
+    }
+
+    /// uniform in `1..=sides`, without modulo bias
+    pub fn roll_die(&mut self, sides: u32) -> u32 {
+        assert!(sides > 0, "a die has one side at least");
+
+        // Lemire's nearly divisionless method
+        let sides = sides as u64;
+        let threshold = (u32::MAX as u64 + 1 - sides) % sides;
+
+        loop {
+            let m = (self.next_u64() >> 32) * sides;
+
+            if (m as u32 as u64) >= threshold {
+                return (m >> 32) as u32 + 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_rolls() {
+        let mut a = DiceRng::seed_from_u64(42);
+        let mut b = DiceRng::seed_from_u64(42);
+        let mut c = DiceRng::seed_from_u64(43);
+
+        let a: Vec<_> = (0..32).map(|_| a.roll_die(20)).collect();
+        let b: Vec<_> = (0..32).map(|_| b.roll_die(20)).collect();
+        let c: Vec<_> = (0..32).map(|_| c.roll_die(20)).collect();
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn roll_die_in_range_and_uniform() {
+        let mut rng = DiceRng::seed_from_u64(7);
+        let mut count = [0_u32; 6];
+
+        for _ in 0..60_000 {
+            let v = rng.roll_die(6);
+            assert!((1..=6).contains(&v));
+            count[v as usize - 1] += 1;
+        }
+
+        for c in count {
+            assert!((9_500..10_500).contains(&c), "{:?}", count);
+        }
+
+        assert_eq!(rng.roll_die(1), 1);
+    }
+}
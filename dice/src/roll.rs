@@ -0,0 +1,404 @@
+use crate::{
+    expr::{Dice, Expr, Keep, Sign, Term},
+    parse::error::ParseResult,
+    rng::DiceRng,
+    verify::{self, Reveal},
+};
+use cgmath::Point3;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// most times a single exploding die could roll again
+pub const EXPLODE_DEPTH: usize = 100;
+
+/// Result of rolling an expression, with every die rolled
+///
+/// Every roll carries its seed and a hash over the expression, seed and dice,
+/// so any client could re-run and verify it, see [`Roll::verify`].
+///
+/// # Example
+/// ```
+/// let roll = dice::Roll::new("4d6kh3+2", 1).unwrap();
+///
+/// let dice = roll.terms()[0].dice();
+/// assert_eq!(dice.len(), 4);
+/// assert_eq!(dice.iter().filter(|d| d.kept()).count(), 3);
+///
+/// assert!((5..=20).contains(&roll.total()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Roll {
+    expr: Expr,
+    seed: u64,
+    terms: Vec<TermRoll>,
+    total: i64,
+    hash: [u8; 32],
+    reveal: Option<Reveal>,
+    /// where the dice were thrown from, if onto a map
+    thrown: Option<Point3<f64>>,
+}
+
+impl Roll {
+    /// parse and roll with a seed
+    pub fn new(s: &str, seed: u64) -> ParseResult<Self> {
+        Ok(crate::parse(s)?.roll(seed))
+    }
+
+    /// parse and roll with the seed derived from a committed secret
+    /// and the salt other players contributed, see [`crate::verify`]
+    pub fn reveal(s: &str, secret: [u8; 32], salt: Vec<u8>) -> ParseResult<Self> {
+        Ok(crate::parse(s)?.reveal(secret, salt))
+    }
+
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// sha256 over the expression, seed and every die
+    pub fn hash(&self) -> [u8; 32] {
+        self.hash
+    }
+
+    /// the secret and salt the seed derived from, if rolled by [`Roll::reveal`]
+    pub fn revealed(&self) -> Option<&Reveal> {
+        self.reveal.as_ref()
+    }
+
+    /// where the dice were thrown from, if by [`Expr::throw`]
+    pub fn thrown(&self) -> Option<Point3<f64>> {
+        self.thrown
+    }
+
+    /// one for each term of the expression, in order
+    pub fn terms(&self) -> &[TermRoll] {
+        &self.terms
+    }
+
+    pub fn total(&self) -> i64 {
+        self.total
+    }
+}
+
+impl Expr {
+    /// roll with the seed derived from a committed secret
+    /// and the salt other players contributed, see [`crate::verify`]
+    pub fn reveal(&self, secret: [u8; 32], salt: Vec<u8>) -> Roll {
+        self.reveal_with(Reveal::new(secret, salt), |seed| self.roll(seed))
+    }
+
+    /// roll with a seed, the same seed rolls the same dice everywhere
+    pub fn roll(&self, seed: u64) -> Roll {
+        self.roll_with(seed, None, |sides, rng| rng.roll_die(sides))
+    }
+
+    /// roll from the seed of the reveal, keeping the reveal in it
+    pub(crate) fn reveal_with(&self, reveal: Reveal, roll: impl FnOnce(u64) -> Roll) -> Roll {
+        let mut ans = roll(reveal.seed(self));
+
+        ans.reveal = Some(reveal);
+
+        ans
+    }
+
+    /// roll every die by `die`, given its sides and the rng of the seed
+    pub(crate) fn roll_with(
+        &self,
+        seed: u64,
+        thrown: Option<Point3<f64>>,
+        mut die: impl FnMut(u32, &mut DiceRng) -> u32,
+    ) -> Roll {
+        let rng = &mut DiceRng::seed_from_u64(seed);
+        let terms: Vec<_> = self
+            .terms()
+            .iter()
+            .map(|(sign, term)| match term {
+                Term::Dice(d) => TermRoll {
+                    sign: *sign,
+                    constant: None,
+                    dice: roll_dice(d, rng, &mut die),
+                },
+                Term::Constant(c) => TermRoll {
+                    sign: *sign,
+                    constant: Some(*c),
+                    dice: Vec::new(),
+                },
+            })
+            .collect();
+
+        let total = terms.iter().map(|t| t.value()).sum();
+
+        Roll {
+            hash: verify::hash_roll(self, seed, &terms, total, thrown),
+            expr: self.clone(),
+            seed,
+            terms,
+            total,
+            reveal: None,
+            thrown,
+        }
+    }
+}
+
+/// Breakdown as `[6, 5, (1), 4] + 2 = 17`, dice dropped in brackets
+impl Display for Roll {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, t) in self.terms.iter().enumerate() {
+            match (i, t.sign) {
+                (0, Sign::Plus) => {}
+                (0, Sign::Minus) => write!(f, "-")?,
+                (_, Sign::Plus) => write!(f, " + ")?,
+                (_, Sign::Minus) => write!(f, " - ")?,
+            }
+
+            write!(f, "{}", t)?;
+        }
+
+        write!(f, " = {}", self.total)
+    }
+}
+
+/// Result of one term of the expression
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TermRoll {
+    sign: Sign,
+    constant: Option<u32>,
+    dice: Vec<DieRoll>,
+}
+
+impl TermRoll {
+    pub fn sign(&self) -> Sign {
+        self.sign
+    }
+
+    /// the value, if the term is a constant modifier
+    pub fn constant(&self) -> Option<u32> {
+        self.constant
+    }
+
+    /// every die rolled, empty for a constant
+    pub fn dice(&self) -> &[DieRoll] {
+        &self.dice
+    }
+
+    /// signed sum of the kept dice, or the constant
+    pub fn value(&self) -> i64 {
+        let v = match self.constant {
+            Some(c) => c as i64,
+            None => self
+                .dice
+                .iter()
+                .filter(|d| d.kept)
+                .map(|d| d.value() as i64)
+                .sum(),
+        };
+
+        self.sign.apply(v)
+    }
+}
+
+impl Display for TermRoll {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(c) = self.constant {
+            return write!(f, "{}", c);
+        }
+
+        write!(f, "[")?;
+        for (i, d) in self.dice.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", d)?;
+        }
+        write!(f, "]")
+    }
+}
+
+/// One die, with every roll of it if it exploded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DieRoll {
+    sides: u32,
+    rolls: Vec<u32>,
+    kept: bool,
+}
+
+impl DieRoll {
+    pub fn sides(&self) -> u32 {
+        self.sides
+    }
+
+    /// the first roll, then one more for each explosion
+    pub fn rolls(&self) -> &[u32] {
+        &self.rolls
+    }
+
+    pub fn value(&self) -> u32 {
+        self.rolls.iter().sum()
+    }
+
+    pub fn exploded(&self) -> bool {
+        self.rolls.len() > 1
+    }
+
+    /// counted into the total, not dropped by keep
+    pub fn kept(&self) -> bool {
+        self.kept
+    }
+}
+
+impl Display for DieRoll {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rolls: Vec<_> = self.rolls.iter().map(|r| r.to_string()).collect();
+
+        if self.kept {
+            write!(f, "{}", rolls.join("+"))
+        } else {
+            write!(f, "({})", rolls.join("+"))
+        }
+    }
+}
+
+fn roll_dice(
+    d: &Dice,
+    rng: &mut DiceRng,
+    die: &mut impl FnMut(u32, &mut DiceRng) -> u32,
+) -> Vec<DieRoll> {
+    let mut dice: Vec<_> = (0..d.count())
+        .map(|_| {
+            let mut rolls = vec![die(d.sides(), rng)];
+
+            while d.explode() && *rolls.last().unwrap() == d.sides() && rolls.len() <= EXPLODE_DEPTH
+            {
+                rolls.push(die(d.sides(), rng));
+            }
+
+            DieRoll {
+                sides: d.sides(),
+                rolls,
+                kept: true,
+            }
+        })
+        .collect();
+
+    if let Some(keep) = d.keep() {
+        // stable, so the earlier of the equal dice is kept
+        let mut order: Vec<_> = (0..dice.len()).collect();
+        match keep {
+            Keep::Highest(_) => order.sort_by_key(|i| std::cmp::Reverse(dice[*i].value())),
+            Keep::Lowest(_) => order.sort_by_key(|i| dice[*i].value()),
+        }
+
+        for i in order.into_iter().skip(d.kept() as usize) {
+            dice[i].kept = false;
+        }
+    }
+
+    dice
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_roll() {
+        assert_eq!(
+            Roll::new("4d6kh3+2", 99).unwrap(),
+            Roll::new("4d6kh3+2", 99).unwrap()
+        );
+    }
+
+    #[test]
+    fn total_in_range() {
+        for seed in 0..200 {
+            let roll = Roll::new("3d6+2-1d4", seed).unwrap();
+
+            assert!((1..=19).contains(&roll.total()), "{}", roll);
+            assert_eq!(roll.terms().len(), 3);
+            assert_eq!(roll.terms()[1].constant(), Some(2));
+            assert!(roll.terms()[2].value() <= -1);
+        }
+    }
+
+    #[test]
+    fn keep_highest_and_lowest() {
+        for seed in 0..200 {
+            let roll = Roll::new("d20 adv", seed).unwrap();
+            let dice = roll.terms()[0].dice();
+            let max = dice.iter().map(|d| d.value()).max().unwrap();
+
+            assert_eq!(dice.len(), 2);
+            assert_eq!(roll.total(), max as i64);
+
+            let roll = Roll::new("4d6dh1", seed).unwrap();
+            let dice = roll.terms()[0].dice();
+            let mut values: Vec<_> = dice.iter().map(|d| d.value() as i64).collect();
+            values.sort();
+
+            assert_eq!(roll.total(), values[..3].iter().sum::<i64>());
+            assert_eq!(dice.iter().filter(|d| !d.kept()).count(), 1);
+        }
+    }
+
+    #[test]
+    fn explode() {
+        let mut exploded = false;
+
+        for seed in 0..500 {
+            let roll = Roll::new("2d4!", seed).unwrap();
+
+            for d in roll.terms()[0].dice() {
+                let (last, init) = d.rolls().split_last().unwrap();
+
+                assert!(init.iter().all(|r| *r == 4));
+                assert!(*last < 4 || d.rolls().len() > EXPLODE_DEPTH);
+                exploded |= d.exploded();
+            }
+        }
+
+        assert!(exploded);
+    }
+
+    #[test]
+    fn percentile() {
+        for seed in 0..100 {
+            assert!((1..=100).contains(&Roll::new("d%", seed).unwrap().total()));
+        }
+    }
+
+    #[test]
+    fn display_breakdown() {
+        let roll = Roll {
+            expr: crate::parse("4d6kh3+2").unwrap(),
+            seed: 0,
+            hash: [0; 32],
+            reveal: None,
+            thrown: None,
+            terms: vec![
+                TermRoll {
+                    sign: Sign::Plus,
+                    constant: None,
+                    dice: [6, 5, 1, 4]
+                        .into_iter()
+                        .map(|v| DieRoll {
+                            sides: 6,
+                            rolls: vec![v],
+                            kept: v != 1,
+                        })
+                        .collect(),
+                },
+                TermRoll {
+                    sign: Sign::Plus,
+                    constant: Some(2),
+                    dice: Vec::new(),
+                },
+            ],
+            total: 17,
+        };
+
+        assert_eq!(roll.to_string(), "[6, 5, (1), 4] + 2 = 17");
+    }
+}
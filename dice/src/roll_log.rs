@@ -0,0 +1,249 @@
+use crate::{
+    verify::{Commitment, VerifyError},
+    Expr, Roll,
+};
+use scenes::OcTree;
+
+/// One roll in the log, with who rolled it
+#[derive(Debug, Clone, PartialEq)]
+pub struct RollEntry {
+    id: usize,
+    player: String,
+    roll: Roll,
+    /// the entry this one re-rolled
+    reroll_of: Option<usize>,
+    /// published before the roll, if it was revealed from a secret
+    commitment: Option<Commitment>,
+}
+
+impl RollEntry {
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn player(&self) -> &str {
+        &self.player
+    }
+
+    pub fn roll(&self) -> &Roll {
+        &self.roll
+    }
+
+    pub fn reroll_of(&self) -> Option<usize> {
+        self.reroll_of
+    }
+
+    pub fn commitment(&self) -> Option<&Commitment> {
+        self.commitment.as_ref()
+    }
+
+    /// Re-run the roll, and check it opens its commitment if any,
+    /// thrown dice are thrown again onto the map, as it was when they were
+    pub fn verify(&self, map: &OcTree) -> Result<(), VerifyError> {
+        self.roll.verify_on(map)?;
+
+        match &self.commitment {
+            Some(c) if !c.opens(&self.roll) => Err(VerifyError::CommitmentDismatch),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Every roll of the session, oldest first
+///
+/// # Example
+/// ```
+/// # use dice::{Roll, RollLog};
+/// let mut log = RollLog::default();
+/// let first = log.push("Alice", Roll::new("d20", 1).unwrap());
+/// log.push("Bob", Roll::new("2d6", 2).unwrap());
+///
+/// let again = log.reroll(first, 3).unwrap();
+/// assert_eq!(log.get(again).unwrap().reroll_of(), Some(first));
+/// assert_eq!(log.filter(Some("Alice")).count(), 2);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RollLog {
+    entries: Vec<RollEntry>,
+    next_id: usize,
+}
+
+impl RollLog {
+    /// return the id of the new entry
+    pub fn push(&mut self, player: impl Into<String>, roll: Roll) -> usize {
+        self.push_entry(player.into(), roll, None, None)
+    }
+
+    /// log a roll revealed from the secret of a commitment published before,
+    /// return the id of the new entry
+    pub fn push_committed(
+        &mut self,
+        player: impl Into<String>,
+        commitment: Commitment,
+        roll: Roll,
+    ) -> usize {
+        self.push_entry(player.into(), roll, None, Some(commitment))
+    }
+
+    /// roll the expression of an entry again with a new seed,
+    /// return the id of the new entry
+    pub fn reroll(&mut self, id: usize, seed: u64) -> Option<usize> {
+        let entry = self.get(id)?;
+        let player = entry.player.clone();
+        let roll = entry.roll.expr().roll(seed);
+
+        Some(self.push_entry(player, roll, Some(id), None))
+    }
+
+    /// roll the expression of an entry again as `roll` does it,
+    /// revealed from the secret of a new commitment,
+    /// return the id of the new entry
+    pub fn reroll_committed(
+        &mut self,
+        id: usize,
+        commitment: Commitment,
+        roll: impl FnOnce(&Expr) -> Roll,
+    ) -> Option<usize> {
+        let entry = self.get(id)?;
+        let player = entry.player.clone();
+        let roll = roll(entry.roll.expr());
+
+        Some(self.push_entry(player, roll, Some(id), Some(commitment)))
+    }
+
+    pub fn get(&self, id: usize) -> Option<&RollEntry> {
+        self.entries.iter().find(|e| e.id == id)
+    }
+
+    /// entries of a player, or everyone's if None
+    pub fn filter<'a>(
+        &'a self,
+        player: Option<&'a str>,
+    ) -> impl DoubleEndedIterator<Item = &'a RollEntry> {
+        self.entries
+            .iter()
+            .filter(move |e| player.map_or(true, |p| e.player == p))
+    }
+
+    /// every player who rolled, in order of their first roll
+    pub fn players(&self) -> Vec<&str> {
+        let mut ans: Vec<&str> = Vec::new();
+
+        for e in &self.entries {
+            if !ans.contains(&e.player.as_str()) {
+                ans.push(&e.player);
+            }
+        }
+
+        ans
+    }
+
+    fn push_entry(
+        &mut self,
+        player: String,
+        roll: Roll,
+        reroll_of: Option<usize>,
+        commitment: Option<Commitment>,
+    ) -> usize {
+        let id = self.next_id;
+
+        self.next_id += 1;
+        self.entries.push(RollEntry {
+            id,
+            player,
+            roll,
+            reroll_of,
+            commitment,
+        });
+
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log() -> RollLog {
+        let mut log = RollLog::default();
+        for (i, player) in ["Bob", "Alice", "Bob", "Carol"].into_iter().enumerate() {
+            log.push(player, Roll::new("d20+1", i as u64).unwrap());
+        }
+        log
+    }
+
+    #[test]
+    fn reroll_ids() {
+        let mut log = log();
+
+        let id = log.reroll(1, 99).unwrap();
+        assert_eq!(id, 4);
+        let entry = log.get(id).unwrap();
+        assert_eq!(entry.player(), "Alice");
+        assert_eq!(entry.reroll_of(), Some(1));
+        assert_eq!(entry.roll(), &Roll::new("d20+1", 99).unwrap());
+
+        // a re-roll of a re-roll points to the one it re-rolled
+        assert_eq!(log.reroll(id, 100), Some(5));
+        assert_eq!(log.get(5).unwrap().reroll_of(), Some(4));
+
+        assert_eq!(log.reroll(42, 0), None);
+        assert_eq!(log.filter(None).count(), 6);
+    }
+
+    #[test]
+    fn committed_rolls_verify() {
+        let mut log = log();
+        let commitment = Commitment::new(&[1; 32]);
+
+        let id = log.push_committed(
+            "Alice",
+            commitment,
+            Roll::reveal("d20", [1; 32], b"salt".to_vec()).unwrap(),
+        );
+        assert_eq!(log.get(id).unwrap().commitment(), Some(&commitment));
+        assert_eq!(log.get(id).unwrap().verify(&OcTree::default()), Ok(()));
+        // plain seeded rolls have nothing to open
+        assert_eq!(log.get(0).unwrap().verify(&OcTree::default()), Ok(()));
+
+        // a secret picked after committing to another
+        let again = log
+            .reroll_committed(id, commitment, |e| e.reveal([2; 32], b"salt".to_vec()))
+            .unwrap();
+        assert_eq!(log.get(again).unwrap().reroll_of(), Some(id));
+        assert_eq!(
+            log.get(again).unwrap().verify(&OcTree::default()),
+            Err(VerifyError::CommitmentDismatch)
+        );
+
+        let again = log
+            .reroll_committed(id, Commitment::new(&[3; 32]), |e| {
+                e.reveal([3; 32], b"more salt".to_vec())
+            })
+            .unwrap();
+        assert_eq!(log.get(again).unwrap().verify(&OcTree::default()), Ok(()));
+    }
+
+    #[test]
+    fn filter_by_player() {
+        let log = log();
+
+        let bob: Vec<_> = log.filter(Some("Bob")).map(|e| e.id()).collect();
+        assert_eq!(bob, [0, 2]);
+        assert_eq!(log.filter(Some("Dave")).count(), 0);
+
+        // newest first, as the panel shows them
+        let all: Vec<_> = log.filter(None).rev().map(|e| e.id()).collect();
+        assert_eq!(all, [3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn players_in_order_of_first_roll() {
+        let mut log = log();
+        assert_eq!(log.players(), ["Bob", "Alice", "Carol"]);
+
+        log.push("Alice", Roll::new("d4", 0).unwrap());
+        assert_eq!(log.players(), ["Bob", "Alice", "Carol"]);
+        assert!(RollLog::default().players().is_empty());
+    }
+}
@@ -0,0 +1,294 @@
+use crate::{
+    expr::Expr,
+    parse::error::ParseError,
+    roll::{Roll, TermRoll},
+};
+use cgmath::Point3;
+use scenes::OcTree;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt::Display;
+
+const SEED_DOMAIN: &[u8] = b"diceshock/seed/v1";
+const ROLL_DOMAIN: &[u8] = b"diceshock/roll/v1";
+const THROWN_DOMAIN: &[u8] = b"diceshock/thrown/v1";
+
+/// Published by a roller before the others contribute salt,
+/// so the roller can't pick a secret after seeing the salt.
+///
+/// 1. the roller picks a random secret, shares `Commitment::new(&secret)`
+/// 2. every other player shares some random salt
+/// 3. the roller shares `Roll::reveal(expression, secret, salt)`
+/// 4. every client checks [`Commitment::opens`] and [`Roll::verify`]
+///
+/// # Example
+/// ```
+/// # use dice::{verify::Commitment, Roll};
+/// let secret = [7; 32];
+/// let commitment = Commitment::new(&secret);
+///
+/// let salt = b"from the other players".to_vec();
+/// let roll = Roll::reveal("d20+3", secret, salt).unwrap();
+///
+/// assert!(commitment.opens(&roll));
+/// assert!(roll.verify().is_ok());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Commitment([u8; 32]);
+
+impl Commitment {
+    pub fn new(secret: &[u8; 32]) -> Self {
+        Self(Sha256::digest(secret).into())
+    }
+
+    pub fn hash(&self) -> [u8; 32] {
+        self.0
+    }
+
+    /// the roll reveals the secret of this commitment
+    pub fn opens(&self, roll: &Roll) -> bool {
+        roll.revealed()
+            .is_some_and(|r| Self::new(&r.secret) == *self)
+    }
+}
+
+/// The secret and salt a seed derived from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reveal {
+    secret: [u8; 32],
+    salt: Vec<u8>,
+}
+
+impl Reveal {
+    pub fn new(secret: [u8; 32], salt: Vec<u8>) -> Self {
+        Self { secret, salt }
+    }
+
+    pub fn secret(&self) -> &[u8; 32] {
+        &self.secret
+    }
+
+    pub fn salt(&self) -> &[u8] {
+        &self.salt
+    }
+
+    /// first 8 bytes of sha256 over the expression, secret and salt
+    pub fn seed(&self, expr: &Expr) -> u64 {
+        let expr = expr.to_string();
+        let mut hasher = Sha256::new();
+
+        hasher.update(SEED_DOMAIN);
+        hasher.update((expr.len() as u32).to_le_bytes());
+        hasher.update(expr.as_bytes());
+        hasher.update(self.secret);
+        hasher.update((self.salt.len() as u32).to_le_bytes());
+        hasher.update(&self.salt);
+
+        let hash: [u8; 32] = hasher.finalize().into();
+
+        u64::from_le_bytes(hash[..8].try_into().unwrap())
+    }
+}
+
+/// sha256 over the canonical expression, seed, every die and total,
+/// and where the dice were thrown from if they were
+pub(crate) fn hash_roll(
+    expr: &Expr,
+    seed: u64,
+    terms: &[TermRoll],
+    total: i64,
+    thrown: Option<Point3<f64>>,
+) -> [u8; 32] {
+    let expr = expr.to_string();
+    let mut hasher = Sha256::new();
+
+    hasher.update(ROLL_DOMAIN);
+    hasher.update((expr.len() as u32).to_le_bytes());
+    hasher.update(expr.as_bytes());
+    hasher.update(seed.to_le_bytes());
+
+    for term in terms {
+        hasher.update(term.value().to_le_bytes());
+        hasher.update((term.dice().len() as u32).to_le_bytes());
+
+        for die in term.dice() {
+            hasher.update([die.kept() as u8]);
+            hasher.update((die.rolls().len() as u32).to_le_bytes());
+
+            for r in die.rolls() {
+                hasher.update(r.to_le_bytes());
+            }
+        }
+    }
+
+    hasher.update(total.to_le_bytes());
+
+    if let Some(at) = thrown {
+        hasher.update(THROWN_DOMAIN);
+        for c in [at.x, at.y, at.z] {
+            hasher.update(c.to_le_bytes());
+        }
+    }
+
+    hasher.finalize().into()
+}
+
+impl Roll {
+    /// re-run the roll from its expression and seed,
+    /// check the dice, the hash and the seed derivation if revealed,
+    /// dice thrown onto a map are checked with [`Roll::verify_on`]
+    pub fn verify(&self) -> Result<(), VerifyError> {
+        if self.thrown().is_some() {
+            return Err(VerifyError::Thrown);
+        }
+
+        self.verify_with(|expr| expr.roll(self.seed()))
+    }
+
+    /// as [`Roll::verify`], throwing the dice again onto the map if they were thrown
+    pub fn verify_on(&self, map: &OcTree) -> Result<(), VerifyError> {
+        match self.thrown() {
+            Some(at) => self.verify_with(|expr| expr.throw(self.seed(), map, at)),
+            None => self.verify(),
+        }
+    }
+
+    fn verify_with(&self, rerun: impl FnOnce(&Expr) -> Roll) -> Result<(), VerifyError> {
+        let expr = crate::parse(&self.expr().to_string())?;
+
+        if let Some(reveal) = self.revealed() {
+            if reveal.seed(&expr) != self.seed() {
+                return Err(VerifyError::SeedDismatch);
+            }
+        }
+
+        let rerolled = rerun(&expr);
+
+        if rerolled.terms() != self.terms() || rerolled.total() != self.total() {
+            return Err(VerifyError::ResultDismatch);
+        }
+        if rerolled.hash() != self.hash() {
+            return Err(VerifyError::HashDismatch);
+        }
+
+        Ok(())
+    }
+
+    /// hash as lowercase hex
+    pub fn hash_hex(&self) -> String {
+        self.hash().iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyError {
+    Parse(ParseError),
+    /// the seed isn't derived from the revealed secret and salt
+    SeedDismatch,
+    /// re-running the roll gives other dice
+    ResultDismatch,
+    HashDismatch,
+    /// the revealed secret isn't the one committed to
+    CommitmentDismatch,
+    /// thrown onto a map, only [`Roll::verify_on`] it could re-run it
+    Thrown,
+}
+
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let msg = match self {
+            VerifyError::Parse(e) => e.to_string(),
+            VerifyError::SeedDismatch => "seed is not derived from the revealed secret".to_owned(),
+            VerifyError::ResultDismatch => "dice differ from re-running the roll".to_owned(),
+            VerifyError::HashDismatch => "hash differs from re-running the roll".to_owned(),
+            VerifyError::CommitmentDismatch => {
+                "revealed secret does not open the commitment".to_owned()
+            }
+            VerifyError::Thrown => "dice thrown onto a map need the map to check".to_owned(),
+        };
+
+        write!(f, "VerifyError-> {}", msg)
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+impl From<ParseError> for VerifyError {
+    fn from(e: ParseError) -> Self {
+        Self::Parse(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tampered(roll: &Roll, f: impl FnOnce(&mut serde_json::Value)) -> Roll {
+        let mut json = serde_json::to_value(roll).unwrap();
+        f(&mut json);
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn seeded_roll_verify() {
+        let roll = Roll::new("4d6kh3+2", 1234).unwrap();
+
+        assert_eq!(roll.verify(), Ok(()));
+        assert_eq!(roll.hash(), Roll::new("4d6 kh3 + 2", 1234).unwrap().hash());
+        assert_ne!(roll.hash(), Roll::new("4d6kh3+2", 1235).unwrap().hash());
+        assert_eq!(roll.hash_hex().len(), 64);
+    }
+
+    #[test]
+    fn shared_roll_survive_the_wire() {
+        let roll = Roll::reveal("2d10!+d%", [1; 32], b"salt".to_vec()).unwrap();
+        let json = serde_json::to_string(&roll).unwrap();
+        let received: Roll = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(received, roll);
+        assert_eq!(received.verify(), Ok(()));
+        assert!(Commitment::new(&[1; 32]).opens(&received));
+        assert!(!Commitment::new(&[2; 32]).opens(&received));
+    }
+
+    #[test]
+    fn detect_tampering() {
+        let roll = Roll::reveal("d20", [9; 32], b"salt".to_vec()).unwrap();
+
+        let forged_total = tampered(&roll, |j| j["total"] = 20.into());
+        assert_eq!(forged_total.verify(), Err(VerifyError::ResultDismatch));
+
+        let forged_die = tampered(&roll, |j| j["terms"][0]["dice"][0]["rolls"][0] = 20.into());
+        assert_eq!(forged_die.verify(), Err(VerifyError::ResultDismatch));
+
+        let forged_seed = tampered(&roll, |j| j["seed"] = 1.into());
+        assert_eq!(forged_seed.verify(), Err(VerifyError::SeedDismatch));
+
+        let forged_hash = tampered(&roll, |j| j["hash"][0] = 0.into());
+        assert_eq!(
+            forged_hash.verify(),
+            if roll.hash()[0] == 0 {
+                Ok(())
+            } else {
+                Err(VerifyError::HashDismatch)
+            }
+        );
+
+        let forged_salt = tampered(&roll, |j| j["reveal"]["salt"] = vec![1, 2, 3].into());
+        assert_eq!(forged_salt.verify(), Err(VerifyError::SeedDismatch));
+    }
+
+    #[test]
+    fn salt_change_seed() {
+        let expr = crate::parse("d20").unwrap();
+
+        assert_ne!(
+            Reveal::new([0; 32], b"a".to_vec()).seed(&expr),
+            Reveal::new([0; 32], b"b".to_vec()).seed(&expr)
+        );
+        assert_ne!(
+            Reveal::new([0; 32], b"a".to_vec()).seed(&expr),
+            Reveal::new([0; 32], b"a".to_vec()).seed(&crate::parse("d12").unwrap())
+        );
+    }
+}
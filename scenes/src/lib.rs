@@ -1 +1,12 @@
-mod scenes;
\ No newline at end of file
+/// map data structures
+pub mod scenes;
+
+pub use scenes::chunk::{ChunkPos, CHUNK_SIZE};
+pub use scenes::history::{Edit, History};
+pub use scenes::instance::Instance;
+pub use scenes::mesh::{self, Mesh};
+pub use scenes::oc_tree::OcTree;
+pub use scenes::selection::{Axis, Selection};
+pub use scenes::style::{Style, StyleId, StyleRegistry};
+pub use scenes::tools;
+pub use scenes::Scene;
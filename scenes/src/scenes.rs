@@ -1,7 +1,19 @@
 use cgmath::Point3;
+use chunk::ChunkPos;
+use instance::Instance;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use style::StyleId;
 
+pub mod chunk;
+pub mod format;
+pub mod history;
 pub mod instance;
+pub mod mesh;
 pub mod oc_tree;
+pub mod selection;
+pub mod style;
+pub mod tools;
 
 /// left handed position
 /// - self at front of the other
@@ -26,4 +38,235 @@ const fn toward(s: Point3<i32>, o: Point3<i32>) -> usize {
     ans
 }
 
-pub(crate) struct Scenes {}
+/// A whole map, owns every Instance placed on it
+///
+/// # Example
+/// ```
+/// # use scenes::{Instance, Scene};
+/// # use cgmath::Point3;
+/// let mut scene = Scene::new();
+///
+/// scene.insert(Instance::new(Point3 { x: 1, y: 2, z: 3 }, "grass".to_owned()));
+///
+/// assert_eq!(scene.len(), 1);
+/// assert_eq!(
+///     scene.get(Point3 { x: 1, y: 2, z: 3 }).map(|v| v.style_id().to_string()),
+///     Some("grass".to_owned())
+/// );
+/// ```
+///
+/// Every edit bumps the revision and marks the chunks it touched,
+/// so a renderer or sync remembering the revision it last saw
+/// only reprocesses what changed since, see [`Scene::changed_since`].
+/// Edits leaving the map as it was change nothing.
+#[derive(Debug, Clone)]
+pub struct Scene {
+    oc_tree: oc_tree::OcTree,
+    /// unique per map, kept by clones
+    id: u64,
+    revision: u64,
+    /// revision each chunk was last touched at, after `forgotten`
+    changed: HashMap<ChunkPos, u64>,
+    /// changes up to it were dropped, past [`Scene::MAX_CHANGED`]
+    forgotten: u64,
+}
+
+static NEXT_SCENE_ID: AtomicU64 = AtomicU64::new(0);
+
+impl Scene {
+    /// scope of a fresh map, it grows as instances placed out of it
+    const DEFAULT_SCOPE: usize = 16;
+    /// chunks whose changes are kept, past it the older half is dropped,
+    /// so no tracker has to tell when it's seen them
+    pub const MAX_CHANGED: usize = 4096;
+
+    pub fn new() -> Self {
+        Self::from_oc_tree(oc_tree::OcTree::from_scope(Self::DEFAULT_SCOPE))
+    }
+
+    pub fn from_oc_tree(oc_tree: oc_tree::OcTree) -> Self {
+        Self {
+            oc_tree,
+            id: NEXT_SCENE_ID.fetch_add(1, Ordering::Relaxed),
+            revision: 0,
+            changed: HashMap::new(),
+            forgotten: 0,
+        }
+    }
+
+    pub fn oc_tree(&self) -> &oc_tree::OcTree {
+        &self.oc_tree
+    }
+
+    pub fn get(&self, pos: Point3<i32>) -> Option<Instance> {
+        self.oc_tree.get(pos)
+    }
+
+    /// return the Instance replaced, if the position was occupied
+    pub fn insert(&mut self, v: Instance) -> Option<Instance> {
+        if self.oc_tree.get(v.pos()).as_ref() == Some(&v) {
+            return Some(v);
+        }
+
+        self.touch([v.pos()]);
+        self.oc_tree.insert(v)
+    }
+
+    pub fn remove(&mut self, pos: Point3<i32>) -> Option<Instance> {
+        let ans = self.oc_tree.remove(pos);
+        if ans.is_some() {
+            self.touch([pos]);
+        }
+        ans
+    }
+
+    /// remove every Instance from `min` to `max`, both included, in one walk of the tree
+    pub fn remove_aabb(&mut self, min: Point3<i32>, max: Point3<i32>) -> Vec<Instance> {
+        let ans = self.oc_tree.remove_aabb(min, max);
+
+        if !ans.is_empty() {
+            self.touch(ans.iter().map(|v| v.pos()));
+        }
+        ans
+    }
+
+    /// remove the Instances at every position, as one edit
+    ///
+    /// One revision for them all, though each is still its own walk of the tree,
+    /// [`Scene::remove_aabb`] walks it once for a box.
+    pub fn remove_all(
+        &mut self,
+        positions: impl IntoIterator<Item = Point3<i32>>,
+    ) -> Vec<Instance> {
+        let ans: Vec<_> = positions
+            .into_iter()
+            .filter_map(|p| self.oc_tree.remove(p))
+            .collect();
+
+        if !ans.is_empty() {
+            self.touch(ans.iter().map(|v| v.pos()));
+        }
+        ans
+    }
+
+    /// change the style of the Instance at pos, return the old style
+    pub fn restyle(&mut self, pos: Point3<i32>, style_id: StyleId) -> Option<StyleId> {
+        let old = self.oc_tree.get(pos)?;
+        if *old.style_id() == style_id {
+            return Some(style_id);
+        }
+
+        self.touch([pos]);
+        self.oc_tree.insert(Instance::new(pos, style_id));
+
+        Some(old.style_id().clone())
+    }
+
+    pub fn iter(&self) -> oc_tree::iter::RefIter<'_> {
+        self.oc_tree.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.oc_tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.oc_tree.is_empty()
+    }
+
+    /// tells maps apart, a clone shares it with the original
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// count of edits so far
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Chunks touched after `revision`, emptied ones included, in order
+    ///
+    /// None if the changes since were forgotten, then reprocess every chunk.
+    pub fn changed_since(&self, revision: u64) -> Option<Vec<ChunkPos>> {
+        if revision < self.forgotten {
+            return None;
+        }
+
+        let mut ans: Vec<_> = self
+            .changed
+            .iter()
+            .filter(|(_, r)| **r > revision)
+            .map(|(c, _)| *c)
+            .collect();
+
+        ans.sort();
+        Some(ans)
+    }
+
+    /// Drop the changes up to `revision`,
+    /// trackers behind it reprocess every chunk
+    fn forget_before(&mut self, revision: u64) {
+        let revision = revision.min(self.revision);
+
+        self.changed.retain(|_, r| *r > revision);
+        self.forgotten = self.forgotten.max(revision);
+    }
+
+    /// chunks holding any Instance, in order
+    pub fn chunks(&self) -> Vec<ChunkPos> {
+        self.iter()
+            .map(|v| ChunkPos::of(v.pos()))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// Instances in a chunk
+    pub fn chunk_instances(&self, chunk: ChunkPos) -> Vec<&Instance> {
+        self.oc_tree
+            .query_aabb(chunk.min_voxel(), chunk.max_voxel())
+    }
+
+    /// one edit, changing every chunk around the positions
+    fn touch(&mut self, positions: impl IntoIterator<Item = Point3<i32>>) {
+        self.revision += 1;
+
+        for pos in positions {
+            for c in ChunkPos::touched_by(pos) {
+                self.changed.insert(c, self.revision);
+            }
+        }
+
+        if self.changed.len() > Self::MAX_CHANGED {
+            let mut revisions: Vec<_> = self.changed.values().copied().collect();
+            let half = revisions.len() / 2;
+            let (_, cut, _) = revisions.select_nth_unstable(half);
+
+            self.forget_before(*cut);
+        }
+    }
+}
+
+/// same Instances, whatever the edits leading there
+impl PartialEq for Scene {
+    fn eq(&self, other: &Self) -> bool {
+        self.oc_tree == other.oc_tree
+    }
+}
+
+impl Eq for Scene {}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> IntoIterator for &'a Scene {
+    type Item = &'a Instance;
+    type IntoIter = oc_tree::iter::RefIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
@@ -0,0 +1,234 @@
+use cgmath::Point3;
+use std::ops::RangeInclusive;
+
+/// edge of a chunk, in voxels
+pub const CHUNK_SIZE: i32 = 16;
+
+/// A cube of `CHUNK_SIZE`³ voxels, the unit maps are re-meshed and synced by
+///
+/// # Example
+/// ```
+/// # use scenes::ChunkPos;
+/// # use cgmath::Point3;
+/// let c = ChunkPos::of(Point3::new(-1, 0, 16));
+///
+/// assert_eq!(c, ChunkPos::new(-1, 0, 1));
+/// assert_eq!(c.min_voxel(), Point3::new(-16, 0, 16));
+/// assert_eq!(c.max_voxel(), Point3::new(-1, 15, 31));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ChunkPos {
+    x: i32,
+    y: i32,
+    z: i32,
+}
+
+impl ChunkPos {
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    /// the chunk a voxel is in
+    pub fn of(pos: Point3<i32>) -> Self {
+        Self {
+            x: pos.x.div_euclid(CHUNK_SIZE),
+            y: pos.y.div_euclid(CHUNK_SIZE),
+            z: pos.z.div_euclid(CHUNK_SIZE),
+        }
+    }
+
+    pub fn x(&self) -> i32 {
+        self.x
+    }
+
+    pub fn y(&self) -> i32 {
+        self.y
+    }
+
+    pub fn z(&self) -> i32 {
+        self.z
+    }
+
+    /// the first voxel of the chunk
+    pub fn min_voxel(&self) -> Point3<i32> {
+        Point3::new(self.x, self.y, self.z) * CHUNK_SIZE
+    }
+
+    /// the last voxel of the chunk, included
+    pub fn max_voxel(&self) -> Point3<i32> {
+        self.min_voxel() + cgmath::Vector3::new(1, 1, 1) * (CHUNK_SIZE - 1)
+    }
+
+    /// Chunks whose mesh a change at `pos` could change,
+    /// its own and the ones across the faces it sits on
+    pub(crate) fn touched_by(pos: Point3<i32>) -> Vec<Self> {
+        let around = |v: i32| -> RangeInclusive<i32> {
+            let c = v.div_euclid(CHUNK_SIZE);
+            match v.rem_euclid(CHUNK_SIZE) {
+                0 => c - 1..=c,
+                r if r == CHUNK_SIZE - 1 => c..=c + 1,
+                _ => c..=c,
+            }
+        };
+        let own = Self::of(pos);
+
+        let mut ans = vec![own];
+        for x in around(pos.x) {
+            for y in around(pos.y) {
+                for z in around(pos.z) {
+                    let c = Self::new(x, y, z);
+                    // across a face only, edges and corners don't share a face
+                    let off = (c.x != own.x) as u8 + (c.y != own.y) as u8 + (c.z != own.z) as u8;
+                    if off == 1 {
+                        ans.push(c);
+                    }
+                }
+            }
+        }
+
+        ans
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Instance, Scene};
+
+    #[test]
+    fn of_negative() {
+        assert_eq!(
+            ChunkPos::of(Point3::new(-16, -17, 15)),
+            ChunkPos::new(-1, -2, 0)
+        );
+        assert_eq!(
+            ChunkPos::new(-1, -2, 0).max_voxel(),
+            Point3::new(-1, -17, 15)
+        );
+    }
+
+    #[test]
+    fn touched_by_borders() {
+        assert_eq!(
+            ChunkPos::touched_by(Point3::new(5, 5, 5)),
+            vec![ChunkPos::new(0, 0, 0)]
+        );
+
+        let mut got = ChunkPos::touched_by(Point3::new(0, 15, 7));
+        got.sort();
+        assert_eq!(
+            got,
+            vec![
+                ChunkPos::new(-1, 0, 0),
+                ChunkPos::new(0, 0, 0),
+                ChunkPos::new(0, 1, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn scene_changed_since() {
+        let mut scene = Scene::new();
+        let start = scene.revision();
+
+        scene.insert(Instance::new(Point3::new(1, 1, 1), "a"));
+        scene.insert(Instance::new(Point3::new(40, 1, 1), "a"));
+        let mid = scene.revision();
+
+        assert_eq!(
+            scene.changed_since(start).unwrap(),
+            vec![ChunkPos::new(0, 0, 0), ChunkPos::new(2, 0, 0)]
+        );
+        assert_eq!(scene.changed_since(mid), Some(Vec::new()));
+
+        scene.restyle(Point3::new(40, 1, 1), "b".into());
+        scene.remove(Point3::new(100, 100, 100));
+        assert_eq!(scene.changed_since(mid), Some(vec![ChunkPos::new(2, 0, 0)]));
+
+        scene.remove(Point3::new(1, 1, 1));
+        assert_eq!(
+            scene.changed_since(mid).unwrap(),
+            vec![ChunkPos::new(0, 0, 0), ChunkPos::new(2, 0, 0)]
+        );
+        assert_eq!(scene.chunks(), vec![ChunkPos::new(2, 0, 0)]);
+    }
+
+    #[test]
+    fn no_op_edits_untracked() {
+        let mut scene = Scene::new();
+        scene.insert(Instance::new(Point3::new(1, 1, 1), "a"));
+        let before = scene.revision();
+
+        scene.insert(Instance::new(Point3::new(1, 1, 1), "a"));
+        scene.restyle(Point3::new(1, 1, 1), "a".into());
+        scene.remove(Point3::new(2, 2, 2));
+
+        assert_eq!(scene.revision(), before);
+
+        scene.insert(Instance::new(Point3::new(1, 1, 1), "b"));
+        assert_eq!(scene.revision(), before + 1);
+    }
+
+    #[test]
+    fn forget_before() {
+        let mut scene = Scene::new();
+        for x in 0..10 {
+            scene.insert(Instance::new(Point3::new(x * 16 + 8, 8, 8), "a"));
+        }
+        let seen = scene.revision();
+        scene.insert(Instance::new(Point3::new(8, 8, 8), "b"));
+
+        scene.forget_before(seen);
+
+        assert_eq!(scene.changed.len(), 1);
+        assert_eq!(
+            scene.changed_since(seen),
+            Some(vec![ChunkPos::new(0, 0, 0)])
+        );
+        assert_eq!(scene.changed_since(seen - 1), None);
+
+        // never past the edits there are
+        scene.forget_before(u64::MAX);
+        assert_eq!(scene.changed_since(scene.revision()), Some(Vec::new()));
+    }
+
+    #[test]
+    fn changes_capped() {
+        let mut scene = Scene::new();
+        let start = scene.revision();
+        let mut recent = Vec::new();
+
+        for x in 0..=Scene::MAX_CHANGED as i32 {
+            scene.insert(Instance::new(Point3::new(x * 16 + 8, 8, 8), "a"));
+            recent.push(scene.revision());
+        }
+
+        assert!(scene.changed.len() <= Scene::MAX_CHANGED);
+        assert_eq!(scene.changed_since(start), None);
+
+        // trackers not far behind go on as before
+        let seen = recent[recent.len() - 10];
+        assert_eq!(scene.changed_since(seen).map(|c| c.len()), Some(9));
+    }
+
+    #[test]
+    fn clone_keeps_tracking() {
+        let mut scene = Scene::new();
+        scene.insert(Instance::new(Point3::new(1, 1, 1), "a"));
+
+        let snapshot = scene.clone();
+        scene.insert(Instance::new(Point3::new(1, 2, 1), "a"));
+
+        assert_eq!(snapshot.id(), scene.id());
+        assert_ne!(Scene::new().id(), scene.id());
+        assert_eq!(
+            scene.changed_since(snapshot.revision()).unwrap(),
+            vec![ChunkPos::new(0, 0, 0)]
+        );
+        assert_eq!(snapshot, {
+            let mut s = scene.clone();
+            s.remove(Point3::new(1, 2, 1));
+            s
+        });
+    }
+}
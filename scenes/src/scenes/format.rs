@@ -0,0 +1,410 @@
+use self::error::{FormatError, FormatResult};
+use super::{instance::Instance, style::StyleId, Scene};
+use cgmath::Point3;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+pub mod error;
+
+/// first bytes of every binary map
+pub const MAGIC: [u8; 4] = *b"DSMP";
+
+/// Version of the map format.
+///
+/// Only bumped for breaking changes:
+/// new sections and new json fields are skipped by older readers,
+/// so they don't need a new version.
+pub const VERSION: u16 = 1;
+
+/// name in the `format` field of a json map
+pub const JSON_FORMAT: &str = "diceshock-map";
+
+const SECTION_STYLES: [u8; 4] = *b"STYL";
+const SECTION_INSTANCES: [u8; 4] = *b"INST";
+
+impl Scene {
+    /// Encode as the compact binary map format.
+    ///
+    /// All the numbers are little endian.
+    ///
+    /// | bytes | content                            |
+    /// |-------|------------------------------------|
+    /// | 4     | magic `DSMP`                       |
+    /// | 2     | version, `u16`                     |
+    /// | ...   | sections, till the end of the data |
+    ///
+    /// Every section is a 4 bytes tag, a `u32` length and `length` bytes of payload,
+    /// sections with unknown tag are skipped.
+    ///
+    /// - `STYL`: `u32` count, then each style id as `u32` length and utf8 bytes
+    /// - `INST`: `u32` count, then each instance as
+    ///   `i32` x, `i32` y, `i32` z and `u32` index into `STYL`
+    ///
+    /// # Example
+    /// ```
+    /// # use scenes::{Instance, Scene};
+    /// # use cgmath::Point3;
+    /// let mut scene = Scene::new();
+    /// scene.insert(Instance::new(Point3 { x: 1, y: 2, z: 3 }, "grass".to_owned()));
+    ///
+    /// let bytes = scene.to_bytes();
+    ///
+    /// assert_eq!(&bytes[..4], b"DSMP");
+    /// assert_eq!(Scene::from_bytes(&bytes).unwrap(), scene);
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut styles: Vec<&StyleId> = Vec::new();
+        let mut style_indexes: HashMap<&StyleId, u32> = HashMap::new();
+        let mut instances = Vec::with_capacity(4 + self.len() * 16);
+
+        instances.extend((self.len() as u32).to_le_bytes());
+
+        for v in self.oc_tree().iter_ordered() {
+            let index = *style_indexes.entry(v.style_id()).or_insert_with(|| {
+                styles.push(v.style_id());
+                styles.len() as u32 - 1
+            });
+            let pos = v.pos();
+
+            instances.extend(pos.x.to_le_bytes());
+            instances.extend(pos.y.to_le_bytes());
+            instances.extend(pos.z.to_le_bytes());
+            instances.extend(index.to_le_bytes());
+        }
+
+        let mut style_table = Vec::new();
+        style_table.extend((styles.len() as u32).to_le_bytes());
+
+        for style in styles {
+            style_table.extend((style.len() as u32).to_le_bytes());
+            style_table.extend(style.as_bytes());
+        }
+
+        let mut ans = Vec::with_capacity(6 + 8 + style_table.len() + 8 + instances.len());
+        ans.extend(MAGIC);
+        ans.extend(VERSION.to_le_bytes());
+        write_section(&mut ans, SECTION_STYLES, &style_table);
+        write_section(&mut ans, SECTION_INSTANCES, &instances);
+
+        ans
+    }
+
+    /// Decode the binary map format, see [`Scene::to_bytes`]
+    pub fn from_bytes(b: &[u8]) -> FormatResult<Self> {
+        let mut reader = Bytes(b);
+
+        if reader.take(4)? != MAGIC {
+            return Err(FormatError::MagicDismatch);
+        }
+
+        let version = reader.u16()?;
+        if version > VERSION {
+            return Err(FormatError::UnsupportedVersion(version));
+        }
+
+        let mut styles = Vec::new();
+        let mut ans = Scene::new();
+
+        while !reader.0.is_empty() {
+            let tag: [u8; 4] = reader.take(4)?.try_into().unwrap();
+            let length = reader.u32()? as usize;
+            let mut payload = Bytes(reader.take(length)?);
+
+            match tag {
+                SECTION_STYLES => {
+                    for _ in 0..payload.u32()? {
+                        let length = payload.u32()? as usize;
+                        let style_id = String::from_utf8(payload.take(length)?.to_vec())?;
+                        styles.push(StyleId::from(style_id));
+                    }
+                }
+                SECTION_INSTANCES => {
+                    for _ in 0..payload.u32()? {
+                        let pos = Point3::new(payload.i32()?, payload.i32()?, payload.i32()?);
+                        let index = payload.u32()?;
+                        let style_id = styles
+                            .get(index as usize)
+                            .ok_or(FormatError::StyleIndexOutOfRange(index))?;
+
+                        ans.insert(Instance::new(pos, style_id.clone()));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(ans)
+    }
+
+    /// Encode as the human-readable json map format,
+    /// one instance per entry, ordered for a stable diff
+    ///
+    /// ```json
+    /// {
+    ///   "format": "diceshock-map",
+    ///   "version": 1,
+    ///   "instances": [
+    ///     { "pos": [1, 2, 3], "style_id": "grass" }
+    ///   ]
+    /// }
+    /// ```
+    ///
+    /// # Example
+    /// ```
+    /// # use scenes::{Instance, Scene};
+    /// # use cgmath::Point3;
+    /// let mut scene = Scene::new();
+    /// scene.insert(Instance::new(Point3 { x: 1, y: 2, z: 3 }, "grass".to_owned()));
+    ///
+    /// let json = scene.to_json();
+    ///
+    /// assert_eq!(Scene::from_json(&json).unwrap(), scene);
+    /// ```
+    pub fn to_json(&self) -> String {
+        let document = JsonMap {
+            format: JSON_FORMAT.to_owned(),
+            version: VERSION,
+            instances: self
+                .oc_tree()
+                .iter_ordered()
+                .map(|v| {
+                    let pos = v.pos();
+                    JsonInstance {
+                        pos: [pos.x, pos.y, pos.z],
+                        style_id: v.style_id().to_string(),
+                    }
+                })
+                .collect(),
+        };
+
+        serde_json::to_string_pretty(&document).expect("a map is always serializable as json")
+    }
+
+    /// Decode the json map format, see [`Scene::to_json`]
+    pub fn from_json(s: &str) -> FormatResult<Self> {
+        let header: JsonHeader = serde_json::from_str(s)?;
+
+        if header.format != JSON_FORMAT {
+            return Err(FormatError::MagicDismatch);
+        }
+        if header.version > VERSION {
+            return Err(FormatError::UnsupportedVersion(header.version));
+        }
+
+        let document: JsonMap = serde_json::from_str(s)?;
+        let mut styles: HashMap<String, StyleId> = HashMap::new();
+        let mut ans = Scene::new();
+
+        for v in document.instances {
+            let [x, y, z] = v.pos;
+            let style_id = styles
+                .entry(v.style_id)
+                .or_insert_with_key(|k| StyleId::from(k.as_str()));
+
+            ans.insert(Instance::new(Point3 { x, y, z }, style_id.clone()));
+        }
+
+        Ok(ans)
+    }
+
+    /// write the binary map format, see [`Scene::to_bytes`]
+    pub fn write_to(&self, mut w: impl Write) -> FormatResult<()> {
+        Ok(w.write_all(&self.to_bytes())?)
+    }
+
+    /// read the binary map format, see [`Scene::to_bytes`]
+    pub fn read_from(mut r: impl Read) -> FormatResult<Self> {
+        let mut b = Vec::new();
+        r.read_to_end(&mut b)?;
+
+        Self::from_bytes(&b)
+    }
+}
+
+fn write_section(b: &mut Vec<u8>, tag: [u8; 4], payload: &[u8]) {
+    b.extend(tag);
+    b.extend((payload.len() as u32).to_le_bytes());
+    b.extend(payload);
+}
+
+/// little endian reader over a byte slice
+struct Bytes<'a>(&'a [u8]);
+
+impl<'a> Bytes<'a> {
+    fn take(&mut self, n: usize) -> FormatResult<&'a [u8]> {
+        if self.0.len() < n {
+            return Err(FormatError::Truncated);
+        }
+
+        let (ans, rest) = self.0.split_at(n);
+        self.0 = rest;
+
+        Ok(ans)
+    }
+
+    fn u16(&mut self) -> FormatResult<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> FormatResult<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i32(&mut self) -> FormatResult<i32> {
+        Ok(i32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonHeader {
+    format: String,
+    version: u16,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JsonMap {
+    format: String,
+    version: u16,
+    instances: Vec<JsonInstance>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct JsonInstance {
+    pos: [i32; 3],
+    style_id: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scene() -> Scene {
+        let mut scene = Scene::new();
+
+        for x in -30..30 {
+            for y in -5..5 {
+                let style_id = if (x + y) % 3 == 0 { "stone" } else { "grass" };
+                scene.insert(Instance::new(Point3 { x, y, z: 0 }, style_id.to_owned()));
+            }
+        }
+
+        scene.insert(Instance::new(
+            Point3 {
+                x: 4000,
+                y: -2,
+                z: 7,
+            },
+            "木".to_owned(),
+        ));
+
+        scene
+    }
+
+    fn same_instances(a: &Scene, b: &Scene) -> bool {
+        a.oc_tree().iter_ordered().eq(b.oc_tree().iter_ordered())
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let scene = scene();
+        let bytes = scene.to_bytes();
+
+        assert!(same_instances(&Scene::from_bytes(&bytes).unwrap(), &scene));
+        assert_eq!(Scene::from_bytes(&bytes).unwrap().to_bytes(), bytes);
+        assert!(same_instances(
+            &Scene::from_bytes(&Scene::new().to_bytes()).unwrap(),
+            &Scene::new()
+        ));
+    }
+
+    #[test]
+    fn bytes_interning_styles() {
+        let bytes = scene().to_bytes();
+
+        assert_eq!(bytes.windows(5).filter(|w| *w == b"stone").count(), 1);
+    }
+
+    #[test]
+    fn bytes_skip_unknown_section() {
+        let scene = scene();
+        let mut bytes = scene.to_bytes();
+
+        write_section(&mut bytes, *b"LITE", &[1, 2, 3]);
+
+        assert!(same_instances(&Scene::from_bytes(&bytes).unwrap(), &scene));
+    }
+
+    #[test]
+    fn bytes_reject_bad_input() {
+        let bytes = scene().to_bytes();
+
+        assert!(matches!(
+            Scene::from_bytes(b"PNG\0\x01\0"),
+            Err(FormatError::MagicDismatch)
+        ));
+        assert!(matches!(
+            Scene::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(FormatError::Truncated)
+        ));
+
+        let mut newer = bytes.clone();
+        newer[4..6].copy_from_slice(&(VERSION + 1).to_le_bytes());
+
+        assert!(matches!(
+            Scene::from_bytes(&newer),
+            Err(FormatError::UnsupportedVersion(v)) if v == VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let scene = scene();
+        let json = scene.to_json();
+
+        assert!(same_instances(&Scene::from_json(&json).unwrap(), &scene));
+        assert_eq!(Scene::from_json(&json).unwrap().to_json(), json);
+    }
+
+    #[test]
+    fn json_forward_compatible() {
+        let json = r#"{
+            "format": "diceshock-map",
+            "version": 1,
+            "author": "gm",
+            "instances": [{ "pos": [1, 2, 3], "style_id": "grass", "light": 3 }]
+        }"#;
+
+        let scene = Scene::from_json(json).unwrap();
+
+        assert_eq!(
+            scene.get(Point3 { x: 1, y: 2, z: 3 }),
+            Some(Instance::new(
+                Point3 { x: 1, y: 2, z: 3 },
+                "grass".to_owned()
+            ))
+        );
+
+        assert!(matches!(
+            Scene::from_json(r#"{ "format": "diceshock-map", "version": 9, "walls": [] }"#),
+            Err(FormatError::UnsupportedVersion(9))
+        ));
+        assert!(matches!(
+            Scene::from_json(r#"{ "format": "tiled", "version": 1, "instances": [] }"#),
+            Err(FormatError::MagicDismatch)
+        ));
+    }
+
+    #[test]
+    fn io_round_trip() {
+        let scene = scene();
+        let mut file = Vec::new();
+
+        scene.write_to(&mut file).unwrap();
+
+        assert!(same_instances(
+            &Scene::read_from(file.as_slice()).unwrap(),
+            &scene
+        ));
+    }
+}
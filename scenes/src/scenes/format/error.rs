@@ -0,0 +1,60 @@
+use std::fmt::Display;
+
+pub type FormatResult<T> = Result<T, FormatError>;
+
+#[derive(Debug)]
+pub enum FormatError {
+    /// not a map file
+    MagicDismatch,
+    /// written by a newer DiceShock
+    UnsupportedVersion(u16),
+    /// the bytes end in the middle of a section
+    Truncated,
+    /// an instance refers to a style not in the style table
+    StyleIndexOutOfRange(u32),
+    Utf8Error(std::string::FromUtf8Error),
+    JsonError(serde_json::Error),
+    IoError(std::io::Error),
+}
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let msg = match self {
+            FormatError::MagicDismatch => "not a DiceShock map".to_owned(),
+            FormatError::UnsupportedVersion(v) => format!(
+                "map format version [{}] is newer than supported [{}]",
+                v,
+                super::VERSION
+            ),
+            FormatError::Truncated => "map data ends unexpectedly".to_owned(),
+            FormatError::StyleIndexOutOfRange(i) => {
+                format!("style index [{}] out of the style table", i)
+            }
+            FormatError::Utf8Error(e) => e.to_string(),
+            FormatError::JsonError(e) => e.to_string(),
+            FormatError::IoError(e) => e.to_string(),
+        };
+
+        write!(f, "{}", msg)
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+impl From<std::string::FromUtf8Error> for FormatError {
+    fn from(e: std::string::FromUtf8Error) -> Self {
+        Self::Utf8Error(e)
+    }
+}
+
+impl From<serde_json::Error> for FormatError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::JsonError(e)
+    }
+}
+
+impl From<std::io::Error> for FormatError {
+    fn from(e: std::io::Error) -> Self {
+        Self::IoError(e)
+    }
+}
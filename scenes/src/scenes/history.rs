@@ -0,0 +1,391 @@
+use super::{instance::Instance, style::StyleId, Scene};
+use cgmath::Point3;
+use std::collections::VecDeque;
+
+/// A change on a Scene, with enough to undo it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Edit {
+    Insert {
+        new: Instance,
+        replaced: Option<Instance>,
+    },
+    Remove(Instance),
+    Restyle {
+        pos: Point3<i32>,
+        from: StyleId,
+        to: StyleId,
+    },
+}
+
+impl Edit {
+    fn apply(&self, scene: &mut Scene) {
+        match self {
+            Edit::Insert { new, .. } => {
+                scene.insert(new.clone());
+            }
+            Edit::Remove(old) => {
+                scene.remove(old.pos());
+            }
+            Edit::Restyle { pos, to, .. } => {
+                scene.restyle(*pos, to.clone());
+            }
+        }
+    }
+
+    fn revert(&self, scene: &mut Scene) {
+        match self {
+            Edit::Insert {
+                replaced: Some(old),
+                ..
+            } => {
+                scene.insert(old.clone());
+            }
+            Edit::Insert {
+                new,
+                replaced: None,
+            } => {
+                scene.remove(new.pos());
+            }
+            Edit::Remove(old) => {
+                scene.insert(old.clone());
+            }
+            Edit::Restyle { pos, from, .. } => {
+                scene.restyle(*pos, from.clone());
+            }
+        }
+    }
+}
+
+/// Undo/redo stacks of the edits on a Scene
+///
+/// Edits between [`History::begin_group`] and [`History::end_group`]
+/// are undone and redone as one step, as a whole brush stroke.
+///
+/// # Example
+/// ```
+/// # use scenes::{History, Instance, Scene};
+/// # use cgmath::Point3;
+/// let mut scene = Scene::new();
+/// let mut history = History::default();
+///
+/// history.begin_group();
+/// for x in 0..3 {
+///     history.insert(&mut scene, Instance::new(Point3 { x, y: 0, z: 0 }, "grass"));
+/// }
+/// history.end_group();
+///
+/// assert_eq!(scene.len(), 3);
+///
+/// history.undo(&mut scene);
+/// assert!(scene.is_empty());
+///
+/// history.redo(&mut scene);
+/// assert_eq!(scene.len(), 3);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct History {
+    undo: VecDeque<Vec<Edit>>,
+    redo: Vec<Vec<Edit>>,
+    /// most steps could be undone
+    depth: usize,
+    group: Vec<Edit>,
+    /// nested begin_group count
+    grouping: usize,
+}
+
+impl History {
+    pub const DEFAULT_DEPTH: usize = 256;
+
+    /// keep at most `depth` steps to undo
+    pub fn new(depth: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            depth,
+            group: Vec::new(),
+            grouping: 0,
+        }
+    }
+
+    /// insert into the scene and record it,
+    /// return the Instance replaced
+    pub fn insert(&mut self, scene: &mut Scene, v: Instance) -> Option<Instance> {
+        let replaced = scene.insert(v.clone());
+
+        if replaced.as_ref() != Some(&v) {
+            self.record(Edit::Insert {
+                new: v,
+                replaced: replaced.clone(),
+            });
+        }
+
+        replaced
+    }
+
+    /// remove from the scene and record it
+    pub fn remove(&mut self, scene: &mut Scene, pos: Point3<i32>) -> Option<Instance> {
+        let old = scene.remove(pos)?;
+
+        self.record(Edit::Remove(old.clone()));
+
+        Some(old)
+    }
+
+    /// remove every Instance from `min` to `max` in one walk of the tree,
+    /// recorded as one step
+    pub fn remove_aabb(
+        &mut self,
+        scene: &mut Scene,
+        min: Point3<i32>,
+        max: Point3<i32>,
+    ) -> Vec<Instance> {
+        let removed = scene.remove_aabb(min, max);
+
+        self.record_removed(removed.clone());
+        removed
+    }
+
+    /// remove the Instances at every position, recorded as one step,
+    /// see [`Scene::remove_all`]
+    pub fn remove_all(
+        &mut self,
+        scene: &mut Scene,
+        positions: impl IntoIterator<Item = Point3<i32>>,
+    ) -> Vec<Instance> {
+        let removed = scene.remove_all(positions);
+
+        self.record_removed(removed.clone());
+        removed
+    }
+
+    /// record Instances already removed from the scene, as one step
+    pub fn record_removed(&mut self, removed: Vec<Instance>) {
+        self.begin_group();
+        for v in removed {
+            self.record(Edit::Remove(v));
+        }
+        self.end_group();
+    }
+
+    /// restyle in the scene and record it,
+    /// return the old style
+    pub fn restyle(
+        &mut self,
+        scene: &mut Scene,
+        pos: Point3<i32>,
+        style_id: StyleId,
+    ) -> Option<StyleId> {
+        let from = scene.restyle(pos, style_id.clone())?;
+
+        if from != style_id {
+            self.record(Edit::Restyle {
+                pos,
+                from: from.clone(),
+                to: style_id,
+            });
+        }
+
+        Some(from)
+    }
+
+    /// start a group, every edit till the matching [`History::end_group`] is one step
+    pub fn begin_group(&mut self) {
+        self.grouping += 1;
+    }
+
+    pub fn end_group(&mut self) {
+        self.grouping = self.grouping.saturating_sub(1);
+
+        if self.grouping == 0 && !self.group.is_empty() {
+            let group = std::mem::take(&mut self.group);
+            self.push(group);
+        }
+    }
+
+    /// revert the last step, false if nothing to undo
+    pub fn undo(&mut self, scene: &mut Scene) -> bool {
+        self.flush_group();
+
+        match self.undo.pop_back() {
+            Some(step) => {
+                for edit in step.iter().rev() {
+                    edit.revert(scene);
+                }
+
+                self.redo.push(step);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// apply the last undone step again, false if nothing to redo
+    pub fn redo(&mut self, scene: &mut Scene) -> bool {
+        self.flush_group();
+
+        match self.redo.pop() {
+            Some(step) => {
+                for edit in step.iter() {
+                    edit.apply(scene);
+                }
+
+                self.undo.push_back(step);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty() || !self.group.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new(self.depth);
+    }
+
+    fn record(&mut self, edit: Edit) {
+        if self.grouping > 0 {
+            self.group.push(edit);
+        } else {
+            self.push(vec![edit]);
+        }
+    }
+
+    fn push(&mut self, step: Vec<Edit>) {
+        self.redo.clear();
+        self.undo.push_back(step);
+
+        while self.undo.len() > self.depth {
+            self.undo.pop_front();
+        }
+    }
+
+    /// close an unfinished group, so undo never splits it
+    fn flush_group(&mut self) {
+        if self.grouping > 0 {
+            self.grouping = 1;
+            self.end_group();
+        }
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_DEPTH)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: i32) -> Point3<i32> {
+        Point3 { x, y: 0, z: 0 }
+    }
+
+    #[test]
+    fn undo_redo_every_edit() {
+        let mut scene = Scene::new();
+        let mut history = History::default();
+
+        history.insert(&mut scene, Instance::new(at(0), "grass"));
+        history.insert(&mut scene, Instance::new(at(1), "grass"));
+        let snapshot = scene.clone();
+
+        history.insert(&mut scene, Instance::new(at(0), "stone"));
+        history.remove(&mut scene, at(1));
+        history.restyle(&mut scene, at(0), StyleId::from("lava"));
+        let edited = scene.clone();
+
+        assert!(history.undo(&mut scene));
+        assert_eq!(scene.get(at(0)), Some(Instance::new(at(0), "stone")));
+        assert!(history.undo(&mut scene));
+        assert!(history.undo(&mut scene));
+        assert_eq!(scene, snapshot);
+
+        assert!(history.redo(&mut scene));
+        assert!(history.redo(&mut scene));
+        assert!(history.redo(&mut scene));
+        assert!(!history.redo(&mut scene));
+        assert_eq!(scene, edited);
+
+        while history.undo(&mut scene) {}
+        assert!(scene.is_empty());
+    }
+
+    #[test]
+    fn no_op_not_recorded() {
+        let mut scene = Scene::new();
+        let mut history = History::default();
+
+        history.insert(&mut scene, Instance::new(at(0), "grass"));
+        history.insert(&mut scene, Instance::new(at(0), "grass"));
+        history.remove(&mut scene, at(5));
+        history.restyle(&mut scene, at(0), StyleId::from("grass"));
+        history.restyle(&mut scene, at(5), StyleId::from("grass"));
+
+        assert!(history.undo(&mut scene));
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn group_is_one_step() {
+        let mut scene = Scene::new();
+        let mut history = History::default();
+
+        history.begin_group();
+        for x in 0..10 {
+            history.insert(&mut scene, Instance::new(at(x), "grass"));
+        }
+        history.begin_group();
+        history.remove(&mut scene, at(3));
+        history.end_group();
+        history.end_group();
+
+        assert_eq!(scene.len(), 9);
+
+        assert!(history.undo(&mut scene));
+        assert!(scene.is_empty());
+        assert!(!history.can_undo());
+
+        assert!(history.redo(&mut scene));
+        assert_eq!(scene.len(), 9);
+        assert_eq!(scene.get(at(3)), None);
+    }
+
+    #[test]
+    fn new_edit_drop_redo() {
+        let mut scene = Scene::new();
+        let mut history = History::default();
+
+        history.insert(&mut scene, Instance::new(at(0), "grass"));
+        history.undo(&mut scene);
+        history.insert(&mut scene, Instance::new(at(1), "grass"));
+
+        assert!(!history.can_redo());
+        assert!(!history.redo(&mut scene));
+    }
+
+    #[test]
+    fn bounded_depth() {
+        let mut scene = Scene::new();
+        let mut history = History::new(3);
+
+        for x in 0..5 {
+            history.insert(&mut scene, Instance::new(at(x), "grass"));
+        }
+
+        let mut undone = 0;
+        while history.undo(&mut scene) {
+            undone += 1;
+        }
+
+        assert_eq!(undone, 3);
+        assert_eq!(scene.len(), 2);
+    }
+}
@@ -1,18 +1,27 @@
+use super::style::StyleId;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Instance {
     position: cgmath::Point3<i32>,
-    style_id: String,
+    style_id: StyleId,
 }
 
 impl Instance {
-    pub fn new(position: cgmath::Point3<i32>, style_id: String) -> Self {
-        Self { position, style_id }
+    pub fn new(position: cgmath::Point3<i32>, style_id: impl Into<StyleId>) -> Self {
+        Self {
+            position,
+            style_id: style_id.into(),
+        }
     }
 
     pub fn pos(&self) -> cgmath::Point3<i32> {
         self.position
     }
 
+    pub fn style_id(&self) -> &StyleId {
+        &self.style_id
+    }
+
     /// left handed position
     /// - self at front of the other
     ///     - +1
@@ -29,7 +38,7 @@ impl Default for Instance {
     fn default() -> Self {
         Self {
             position: cgmath::Point3 { x: 0, y: 0, z: 0 },
-            style_id: "".to_owned(),
+            style_id: StyleId::from(""),
         }
     }
 }
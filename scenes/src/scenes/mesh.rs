@@ -0,0 +1,365 @@
+use super::{chunk::ChunkPos, instance::Instance, oc_tree::OcTree, style::StyleId};
+use cgmath::{ElementWise, Point3};
+use std::collections::{BTreeMap, HashMap};
+
+/// Triangles of all the visible faces of one style
+///
+/// Quads are counter-clockwise seen from outside, four corners each,
+/// so every corner carries the normal of its quad.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Mesh {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    indices: Vec<u32>,
+}
+
+impl Mesh {
+    pub fn positions(&self) -> &[[f32; 3]] {
+        &self.positions
+    }
+
+    /// one for each position
+    pub fn normals(&self) -> &[[f32; 3]] {
+        &self.normals
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    pub fn quad_count(&self) -> usize {
+        self.positions.len() / 4
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// the quads of `other` after its own
+    fn append(&mut self, other: Self) {
+        let start = self.positions.len() as u32;
+
+        self.positions.extend(other.positions);
+        self.normals.extend(other.normals);
+        self.indices
+            .extend(other.indices.into_iter().map(|i| start + i));
+    }
+
+    fn push_quad(&mut self, corners: [[f32; 3]; 4], normal: [f32; 3]) {
+        let start = self.positions.len() as u32;
+
+        self.positions.extend(corners);
+        self.normals.extend([normal; 4]);
+        self.indices.extend([0, 1, 2, 0, 2, 3].map(|i| start + i));
+    }
+}
+
+/// Mesh of every instance of the tree, see [`mesh_aabb`]
+///
+/// Swept chunk by chunk, each only over the bounds of what's in it,
+/// so instances far apart don't sweep all the empty space between.
+/// Quads don't merge across chunk borders.
+pub fn mesh(tree: &OcTree) -> BTreeMap<StyleId, Mesh> {
+    let mut bounds: BTreeMap<ChunkPos, (Point3<i32>, Point3<i32>)> = BTreeMap::new();
+
+    for p in tree.iter().map(Instance::pos) {
+        bounds
+            .entry(ChunkPos::of(p))
+            .and_modify(|(min, max)| {
+                *min = Point3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+                *max = Point3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+            })
+            .or_insert((p, p));
+    }
+
+    let mut ans: BTreeMap<StyleId, Mesh> = BTreeMap::new();
+
+    for (min, max) in bounds.into_values() {
+        for (style, m) in mesh_aabb(tree, min, max) {
+            ans.entry(style).or_default().append(m);
+        }
+    }
+
+    ans
+}
+
+/// Greedy mesh of the instances inside the AABB-Box, `min` and `max` included
+///
+/// A face is dropped if another voxel covers it, inside the box or not,
+/// then faces of a style side by side merge into as few quads as they can.
+///
+/// # Example
+/// ```
+/// # use scenes::{mesh, Instance, OcTree};
+/// # use cgmath::Point3;
+/// let mut a = OcTree::from_scope(4);
+///
+/// for x in 0..4 {
+///     for y in 0..4 {
+///         a.insert(Instance::new(Point3 { x, y, z: 0 }, "stone"));
+///     }
+/// }
+///
+/// let meshes = mesh::mesh(&a);
+///
+/// // a flat slab is a box, whatever its size
+/// assert_eq!(meshes["stone"].quad_count(), 6);
+/// ```
+pub fn mesh_aabb(tree: &OcTree, min: Point3<i32>, max: Point3<i32>) -> BTreeMap<StyleId, Mesh> {
+    // one around too, to know what covers the faces on the border
+    let cells: HashMap<Point3<i32>, &StyleId> = tree
+        .query_aabb(min.add_element_wise(-1), max.add_element_wise(1))
+        .into_iter()
+        .map(|v| (v.pos(), v.style_id()))
+        .collect();
+    let (min, max): ([i32; 3], [i32; 3]) = (min.into(), max.into());
+
+    let mut ans: BTreeMap<StyleId, Mesh> = BTreeMap::new();
+
+    for axis in 0..3 {
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+        let width = (max[u] - min[u] + 1) as usize;
+        let height = (max[v] - min[v] + 1) as usize;
+
+        for step in [1, -1] {
+            for layer in min[axis]..=max[axis] {
+                // style of each face of the layer seen from this side, if visible
+                let mut mask: Vec<Option<&StyleId>> = vec![None; width * height];
+
+                for j in 0..height {
+                    for i in 0..width {
+                        let mut p = [0; 3];
+                        p[axis] = layer;
+                        p[u] = min[u] + i as i32;
+                        p[v] = min[v] + j as i32;
+
+                        let mut n = p;
+                        n[axis] += step;
+
+                        if !cells.contains_key(&n.into()) {
+                            mask[j * width + i] = cells.get(&p.into()).copied();
+                        }
+                    }
+                }
+
+                let plane = if step > 0 { layer + 1 } else { layer };
+
+                for (style, Rect { i, j, w, h }) in merge(&mut mask, width, height) {
+                    let corner = |du: usize, dv: usize| {
+                        let mut c = [0.0; 3];
+                        c[axis] = plane as f32;
+                        c[u] = (min[u] + (i + du) as i32) as f32;
+                        c[v] = (min[v] + (j + dv) as i32) as f32;
+                        c
+                    };
+                    let mut normal = [0.0; 3];
+                    normal[axis] = step as f32;
+
+                    // u × v points up the axis, so reverse for the other side
+                    let corners = if step > 0 {
+                        [corner(0, 0), corner(w, 0), corner(w, h), corner(0, h)]
+                    } else {
+                        [corner(0, 0), corner(0, h), corner(w, h), corner(w, 0)]
+                    };
+
+                    ans.entry(style.clone())
+                        .or_default()
+                        .push_quad(corners, normal);
+                }
+            }
+        }
+    }
+
+    ans
+}
+
+/// faces `i..i + w` along u and `j..j + h` along v of a layer
+struct Rect {
+    i: usize,
+    j: usize,
+    w: usize,
+    h: usize,
+}
+
+/// Take rectangles of the same style out of the mask greedily,
+/// each as wide as it goes, then as high as the whole width allows
+fn merge<'a>(
+    mask: &mut [Option<&'a StyleId>],
+    width: usize,
+    height: usize,
+) -> Vec<(&'a StyleId, Rect)> {
+    let mut ans = Vec::new();
+
+    for j in 0..height {
+        let mut i = 0;
+
+        while i < width {
+            let style = match mask[j * width + i] {
+                Some(s) => s,
+                None => {
+                    i += 1;
+                    continue;
+                }
+            };
+
+            let w = (i..width)
+                .take_while(|x| mask[j * width + x] == Some(style))
+                .count();
+            let h = (j..height)
+                .take_while(|y| (i..i + w).all(|x| mask[y * width + x] == Some(style)))
+                .count();
+
+            for y in j..j + h {
+                mask[y * width + i..y * width + i + w].fill(None);
+            }
+
+            ans.push((style, Rect { i, j, w, h }));
+            i += w;
+        }
+    }
+
+    ans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{InnerSpace, Vector3};
+    use proptest::prelude::*;
+
+    fn tree_of(positions: &[(Point3<i32>, &str)]) -> OcTree {
+        let mut ans = OcTree::from_scope(4);
+        for (p, s) in positions {
+            ans.insert(Instance::new(*p, *s));
+        }
+        ans
+    }
+
+    /// area of every quad of the meshes
+    fn area(meshes: &BTreeMap<StyleId, Mesh>) -> f32 {
+        meshes
+            .values()
+            .flat_map(|m| m.positions().chunks(4))
+            .map(|q| {
+                let [a, b, c] = [0, 1, 2].map(|i| Vector3::from(q[i]));
+                (b - a).cross(c - a).magnitude()
+            })
+            .sum()
+    }
+
+    /// faces with no voxel on the other side
+    fn exposed(tree: &OcTree) -> usize {
+        let dirs = [
+            Vector3::unit_x(),
+            -Vector3::unit_x(),
+            Vector3::unit_y(),
+            -Vector3::unit_y(),
+            Vector3::unit_z(),
+            -Vector3::unit_z(),
+        ];
+
+        tree.iter()
+            .map(|v| {
+                dirs.iter()
+                    .filter(|d| tree.get(v.pos() + **d).is_none())
+                    .count()
+            })
+            .sum()
+    }
+
+    #[test]
+    fn single_voxel() {
+        let meshes = mesh(&tree_of(&[(Point3::new(1, 2, 3), "a")]));
+        let m = &meshes["a"];
+
+        assert_eq!(m.quad_count(), 6);
+        assert_eq!((m.positions().len(), m.indices().len()), (24, 36));
+        assert!(m.positions().iter().all(|p| (1.0..=2.0).contains(&p[0])
+            && (2.0..=3.0).contains(&p[1])
+            && (3.0..=4.0).contains(&p[2])));
+    }
+
+    #[test]
+    fn cull_and_merge() {
+        let row: Vec<_> = (0..5).map(|x| (Point3::new(x, 0, 0), "a")).collect();
+        let meshes = mesh(&tree_of(&row));
+
+        assert_eq!(meshes["a"].quad_count(), 6);
+        assert_eq!(area(&meshes), 22.0);
+    }
+
+    #[test]
+    fn styles_not_merged() {
+        let meshes = mesh(&tree_of(&[
+            (Point3::new(0, 0, 0), "a"),
+            (Point3::new(1, 0, 0), "b"),
+        ]));
+
+        // the faces between are hidden whatever the styles
+        assert_eq!(meshes["a"].quad_count(), 5);
+        assert_eq!(meshes["b"].quad_count(), 5);
+    }
+
+    #[test]
+    fn region_culled_by_outside() {
+        let row: Vec<_> = (0..4).map(|x| (Point3::new(x, 0, 0), "a")).collect();
+        let tree = tree_of(&row);
+
+        let left = mesh_aabb(&tree, Point3::new(0, 0, 0), Point3::new(1, 0, 0));
+        let right = mesh_aabb(&tree, Point3::new(2, 0, 0), Point3::new(3, 0, 0));
+
+        assert_eq!(area(&left) + area(&right), area(&mesh(&tree)));
+        assert_eq!(left["a"].quad_count(), 5);
+    }
+
+    #[test]
+    fn far_apart() {
+        let tree = tree_of(&[
+            (Point3::new(0, 0, 0), "a"),
+            (Point3::new(1_000_000, -1_000_000, 1_000_000), "a"),
+        ]);
+
+        // would sweep 10^18 cells over the bounds of both
+        assert_eq!(mesh(&tree)["a"].quad_count(), 12);
+    }
+
+    #[test]
+    fn empty() {
+        assert!(mesh(&OcTree::from_scope(4)).is_empty());
+    }
+
+    proptest! {
+        #[test]
+        fn faces_exposed_and_outward(
+            cells in prop::collection::vec((0..4, 0..4, 0..4, 0..2usize), 1..40)
+        ) {
+            let cells: Vec<_> = cells
+                .into_iter()
+                .map(|(x, y, z, s)| (Point3::new(x, y, z), ["a", "b"][s]))
+                .collect();
+            let tree = tree_of(&cells);
+            let meshes = mesh(&tree);
+
+            prop_assert_eq!(area(&meshes) as usize, exposed(&tree));
+
+            for m in meshes.values() {
+                for (q, n) in m.positions().chunks(4).zip(m.normals().chunks(4)) {
+                    let [a, b, c] = [0, 1, 2].map(|i| Vector3::from(q[i]));
+                    let n = Vector3::from(n[0]);
+
+                    prop_assert!((b - a).cross(c - a).normalize().dot(n) > 0.99);
+
+                    // the voxel behind the quad is there, the one in front isn't
+                    let centre = (Vector3::from(q[0]) + Vector3::from(q[2])) / 2.0;
+                    let behind = centre - n * 0.5;
+                    let front = centre + n * 0.5;
+                    let cell = |p: Vector3<f32>| {
+                        Point3::new(p.x.floor() as i32, p.y.floor() as i32, p.z.floor() as i32)
+                    };
+                    prop_assert!(tree.get(cell(behind)).is_some());
+                    prop_assert!(tree.get(cell(front)).is_none());
+                }
+            }
+        }
+    }
+}
@@ -2,6 +2,7 @@ use super::instance::Instance;
 use cgmath::Point3;
 
 pub mod iter;
+pub mod query;
 
 const CENTRAL: Point3<i32> = Point3 { x: 0, y: 0, z: 0 };
 
@@ -10,10 +11,12 @@ pub struct OcTree {
     __value__: Node,
     __len__: usize,
     __scope__: usize,
+    __central__: Point3<i32>,
 }
 
 impl OcTree {
-    /// Create a OcTree with a sized AABB-Box
+    /// Create a OcTree with a sized AABB-Box,
+    /// scope round up to the power of two, as 1 at least
     ///
     /// # Performance
     /// ```ignore
@@ -30,18 +33,19 @@ impl OcTree {
     ///
     /// // ...2.pos() = P3(0,0,4)
     /// // out of this scope,
-    /// // would extend the scope as 8 (4*2)
-    /// // its a AABB-Box as:
-    /// // frontX: 7, rightY: 7, topZ: 7,
-    /// // backX: -8, leftY: -8, bottom: -8,
+    /// // would extend the scope as 8 (4*2) toward ...2,
+    /// // the old AABB-Box become one of its octants
+    /// // its a AABB-Box central at P3(4,4,4) as:
+    /// // frontX: 11, rightY: 11, topZ: 11,
+    /// // backX: -4, leftY: -4, bottom: -4,
     /// a.insert(...2);
     /// ```
     ///
     /// # Example
     /// ```
-    /// # use super::instance::Instance;
+    /// # use scenes::{Instance, OcTree};
     /// # use cgmath::Point3;
-    /// let mut a = Self::from_scope(3);
+    /// let mut a = OcTree::from_scope(3);
     ///
     /// a.insert(Instance::default());
     ///
@@ -51,26 +55,50 @@ impl OcTree {
     /// );
     /// ```
     pub fn from_scope(scope: usize) -> Self {
-        for i in (0..).map(|i| (2.0_f32.powf(scope as f32)) as usize) {
-            if i >= scope {
-                return Self {
-                    __value__: Node::default(),
-                    __len__: 0,
-                    __scope__: i,
-                };
-            }
+        Self {
+            __value__: Node::default(),
+            __len__: 0,
+            __scope__: scope.max(1).next_power_of_two(),
+            __central__: CENTRAL,
         }
+    }
 
-        Self::default()
+    /// Create a OcTree with a sized AABB-Box around `central`,
+    /// for maps drawn far from the origin
+    ///
+    /// # Example
+    /// ```
+    /// # use scenes::{Instance, OcTree};
+    /// # use cgmath::Point3;
+    /// let mut a = OcTree::with_central(Point3 { x: 5000, y: 0, z: 0 }, 3);
+    ///
+    /// a.insert(Instance::new(Point3 { x: 5002, y: 1, z: 0 }, "".to_owned()));
+    ///
+    /// assert_eq!(a.scope(), OcTree::from_scope(3).scope());
+    /// ```
+    pub fn with_central(central: Point3<i32>, scope: usize) -> Self {
+        Self {
+            __central__: central,
+            ..Self::from_scope(scope)
+        }
+    }
+
+    /// the AABB-Box covers `central - scope..central + scope` on every axis
+    pub fn scope(&self) -> usize {
+        self.__scope__
+    }
+
+    pub fn central(&self) -> Point3<i32> {
+        self.__central__
     }
 
     /// get the Instance by its position
     ///
     /// # Example
     /// ```
-    /// # use super::instance::Instance;
+    /// # use scenes::{Instance, OcTree};
     /// # use cgmath::Point3;
-    /// let mut a = Self::from_scope(3);
+    /// let mut a = OcTree::from_scope(3);
     ///
     /// a.insert(Instance::default());
     ///
@@ -85,79 +113,172 @@ impl OcTree {
 
     /// return a iterator for all the instances.
     ///
-    /// Index as x++ -overflow-> y++ -overflow-> z++
+    /// Walk through the branches, only visit the occupied ones,
+    /// ordered as the tree stored them.
     ///
     /// # Examples
     /// ```
-    /// # use super::instance::Instance;
+    /// # use scenes::{Instance, OcTree};
     /// # use cgmath::Point3;
-    /// let mut a = Self::from_scope(3);
+    /// let mut a = OcTree::from_scope(3);
     ///
     /// a.insert(Instance::default());
     ///
-    /// assert_eq!(a.iter().next(), Some(Instance::default()));
+    /// assert_eq!(a.iter().next(), Some(&Instance::default()));
     /// ```
-    pub fn iter(&self) -> iter::RefIter {
+    pub fn iter(&self) -> iter::RefIter<'_> {
         iter::RefIter::new(self)
     }
 
+    /// return a iterator for all the instances, deterministic for serialization.
+    ///
+    /// Index as x++ -overflow-> y++ -overflow-> z++
+    ///
+    /// # Examples
+    /// ```
+    /// # use scenes::{Instance, OcTree};
+    /// # use cgmath::Point3;
+    /// let mut a = OcTree::from_scope(3);
+    ///
+    /// a.insert(Instance::new(Point3 { x: 0, y: 0, z: 1 }, "".to_owned()));
+    /// a.insert(Instance::new(Point3 { x: 0, y: 1, z: 0 }, "".to_owned()));
+    /// a.insert(Instance::new(Point3 { x: 1, y: 0, z: 0 }, "".to_owned()));
+    ///
+    /// assert_eq!(
+    ///     a.iter_ordered().map(|v| v.pos()).collect::<Vec<_>>(),
+    ///     vec![
+    ///         Point3 { x: 1, y: 0, z: 0 },
+    ///         Point3 { x: 0, y: 1, z: 0 },
+    ///         Point3 { x: 0, y: 0, z: 1 },
+    ///     ]
+    /// );
+    /// ```
+    pub fn iter_ordered(&self) -> iter::OrderedIter<'_> {
+        iter::OrderedIter::new(self)
+    }
+
     /// - loop:
     ///     - if in scope {driect insert}
     ///     - else {extend}
     ///
+    /// return the Instance replaced, if the position was occupied
+    ///
     /// # Example
     /// ```
-    /// # use super::instance::Instance;
+    /// # use scenes::{Instance, OcTree};
     /// # use cgmath::Point3;
-    /// let mut a = Self::from_scope(3);
+    /// let mut a = OcTree::from_scope(3);
     ///
-    /// a.insert(Instance::default());
+    /// assert_eq!(a.insert(Instance::default()), None);
     ///
     /// assert_eq!(
     ///     a.get(Point3 { x: 0, y: 0, z: 0 }),
     ///     Some(Instance::default())
     /// );
+    ///
+    /// let stone = Instance::new(Point3 { x: 0, y: 0, z: 0 }, "stone".to_owned());
+    ///
+    /// assert_eq!(a.insert(stone), Some(Instance::default()));
+    /// assert_eq!(a.len(), 1);
     /// ```
-    pub fn insert(&mut self, v: Instance) {
-        self.__len__ += 1;
-
+    pub fn insert(&mut self, v: Instance) -> Option<Instance> {
         loop {
             if self.is_in_scope(&v.pos()) {
-                self.__value__.insert(v, CENTRAL, self.__scope__);
-                break;
+                let ans = self.__value__.insert(v, self.__central__, self.__scope__);
+
+                if ans.is_none() {
+                    self.__len__ += 1;
+                }
+
+                return ans;
             } else {
-                self.update_scope(self.__scope__ * 2);
+                self.grow_toward(v.pos());
             }
         }
     }
 
+    /// remove the Instance at the position,
+    /// branches left with one or zero Instance collapse back into a leaf
+    ///
+    /// # Example
+    /// ```
+    /// # use scenes::{Instance, OcTree};
+    /// # use cgmath::Point3;
+    /// let mut a = OcTree::from_scope(3);
+    ///
+    /// a.insert(Instance::default());
+    ///
+    /// assert_eq!(a.remove(Point3 { x: 0, y: 0, z: 0 }), Some(Instance::default()));
+    /// assert_eq!(a.get(Point3 { x: 0, y: 0, z: 0 }), None);
+    /// ```
+    pub fn remove(&mut self, pos: Point3<i32>) -> Option<Instance> {
+        let ans = self.__value__.remove(pos);
+
+        if ans.is_some() {
+            self.__len__ -= 1;
+        }
+
+        ans
+    }
+
+    /// count of the instances
+    pub fn len(&self) -> usize {
+        self.__len__
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.__len__ == 0
+    }
+
     fn is_in_scope(&self, p: &Point3<i32>) -> bool {
-        p.x > -(self.__scope__ as i32)
-            && p.x <= self.__scope__ as i32
-            && p.y > -(self.__scope__ as i32)
-            && p.y <= self.__scope__ as i32
-            && p.z > -(self.__scope__ as i32)
-            && p.z <= self.__scope__ as i32
+        let (c, scope) = (self.__central__, self.__scope__ as i32);
+
+        p.x >= c.x - scope
+            && p.x < c.x + scope
+            && p.y >= c.y - scope
+            && p.y < c.y + scope
+            && p.z >= c.z - scope
+            && p.z < c.z + scope
     }
 
-    fn update_scope(&mut self, new_scope: usize) {
-        let mut new = Self::from_scope(new_scope);
+    /// Double the scope toward `p`,
+    /// the old root become the octant of the new one it covers.
+    ///
+    /// An empty OcTree just re-root around `p`.
+    fn grow_toward(&mut self, p: Point3<i32>) {
+        if self.is_empty() {
+            self.__central__ = p;
+            return;
+        }
+
+        let scope = self.__scope__ as i32;
+        let old_central = self.__central__;
+        let mut central = old_central;
+
+        central.x += if p.x >= old_central.x { scope } else { -scope };
+        central.y += if p.y >= old_central.y { scope } else { -scope };
+        central.z += if p.z >= old_central.z { scope } else { -scope };
+
+        if let Node::Trunk(_) = self.__value__ {
+            let mut trunk = Trunk {
+                central,
+                branches: Default::default(),
+            };
 
-        for i in self.iter() {
-            new.insert(i);
+            *trunk.branches[super::toward(old_central, central)] =
+                std::mem::take(&mut self.__value__);
+
+            self.__value__ = Node::Trunk(trunk);
         }
 
-        *self = new;
+        self.__central__ = central;
+        self.__scope__ *= 2;
     }
 }
 
 impl Default for OcTree {
     fn default() -> Self {
-        Self {
-            __value__: Node::default(),
-            __len__: 0,
-            __scope__: 0,
-        }
+        Self::from_scope(1)
     }
 }
 
@@ -168,51 +289,72 @@ enum Node {
 }
 
 impl Node {
-    fn new(v: Instance) -> Self {
-        Self::Leaf(Some(v))
-    }
-
-    fn insert(&mut self, v: Instance, central: Point3<i32>, offset: usize) {
+    /// `central` and `offset` describe the AABB-Box of this node,
+    /// as `central - offset..central + offset` on every axis.
+    fn insert(&mut self, v: Instance, central: Point3<i32>, offset: usize) -> Option<Instance> {
         match self {
             Node::Trunk(t) => t.insert(offset, v),
+            Node::Leaf(Some(vo)) if vo.pos() == v.pos() => Some(std::mem::replace(vo, v)),
             Node::Leaf(Some(vo)) => {
-                *self = Node::Trunk(Trunk::new(
-                    Self::__get_new_central__(central, offset, vo.toward(central)),
-                    offset,
-                    (vo.clone(), v),
-                ))
+                *self = Node::Trunk(Trunk::new(central, offset, (vo.clone(), v)));
+                None
+            }
+            Node::Leaf(None) => {
+                *self = Node::Leaf(Some(v));
+                None
             }
-            Node::Leaf(None) => *self = Node::Leaf(Some(v)),
         }
     }
 
     fn get(&self, pos: Point3<i32>) -> Option<Instance> {
         match self {
             Node::Trunk(t) => t.get(pos),
-            Node::Leaf(Some(v)) => Some(v.clone()),
-            Node::Leaf(None) => None,
+            Node::Leaf(Some(v)) if v.pos() == pos => Some(v.clone()),
+            Node::Leaf(_) => None,
+        }
+    }
+
+    /// remove the Instance at pos,
+    /// a Trunk left with one or zero Instance would collapse back into a Leaf
+    fn remove(&mut self, pos: Point3<i32>) -> Option<Instance> {
+        match self {
+            Node::Trunk(t) => {
+                let ans = t.remove(pos);
+
+                if ans.is_some() {
+                    if let Some(leaf) = t.collapse() {
+                        *self = leaf;
+                    }
+                }
+
+                ans
+            }
+            Node::Leaf(v) if v.as_ref().map(|v| v.pos()) == Some(pos) => v.take(),
+            Node::Leaf(_) => None,
         }
     }
 
+    /// central of the branch at `toward`, for a node at `last_central` sized by `offset`
     fn __get_new_central__(last_central: Point3<i32>, offset: usize, toward: usize) -> Point3<i32> {
         let mut ans = last_central;
+        let offset = (offset / 2) as i32;
 
-        if toward % 2 != 0 {
-            ans.x -= offset as i32
+        if toward & 1 != 0 {
+            ans.x += offset
         } else {
-            ans.x += offset as i32
+            ans.x -= offset
         };
 
-        if toward % 4 / 2 != 0 {
-            ans.y -= offset as i32
+        if toward & 2 != 0 {
+            ans.y += offset
         } else {
-            ans.y += offset as i32
+            ans.y -= offset
         };
 
-        if toward / 4 != 0 {
-            ans.z -= offset as i32
+        if toward & 4 != 0 {
+            ans.z += offset
         } else {
-            ans.z += offset as i32
+            ans.z -= offset
         };
 
         ans
@@ -233,48 +375,59 @@ struct Trunk {
 
 impl Trunk {
     fn new(central: Point3<i32>, offset: usize, v: (Instance, Instance)) -> Self {
-        let mut branches = [
-            Box::from(Node::default()),
-            Box::from(Node::default()),
-            Box::from(Node::default()),
-            Box::from(Node::default()),
-            Box::from(Node::default()),
-            Box::from(Node::default()),
-            Box::from(Node::default()),
-            Box::from(Node::default()),
-        ];
-
-        let v0t = v.0.toward(central);
-        let v1t = v.1.toward(central);
-
-        if v0t == v1t {
-            let mut node = Box::from(Node::default());
-
-            node.insert(v.0, central, offset);
-            node.insert(v.1, central, offset);
-
-            branches[v0t] = Box::from(node);
-        } else {
-            branches[v0t] = Box::from(Node::new(v.0));
-            branches[v1t] = Box::from(Node::new(v.1));
-        }
+        let mut ans = Self {
+            central,
+            branches: Default::default(),
+        };
 
-        Self { central, branches }
+        ans.insert(offset, v.0);
+        ans.insert(offset, v.1);
+
+        ans
     }
 
-    fn insert(&mut self, offset: usize, v: Instance) {
-        self.branches[v.toward(self.central)].insert(v, self.central, offset / 2)
+    fn insert(&mut self, offset: usize, v: Instance) -> Option<Instance> {
+        let toward = v.toward(self.central);
+        let central = Node::__get_new_central__(self.central, offset, toward);
+
+        self.branches[toward].insert(v, central, offset / 2)
     }
 
     fn get(&self, pos: Point3<i32>) -> Option<Instance> {
         let toward = super::toward(pos, self.central);
         self.branches[toward].get(pos)
     }
+
+    fn remove(&mut self, pos: Point3<i32>) -> Option<Instance> {
+        let toward = super::toward(pos, self.central);
+        self.branches[toward].remove(pos)
+    }
+
+    /// take the only Instance out as a Leaf,
+    /// None if any branch is a Trunk or more than one Instance left
+    fn collapse(&mut self) -> Option<Node> {
+        let mut only = None;
+
+        for (i, branch) in self.branches.iter().enumerate() {
+            match branch.as_ref() {
+                Node::Trunk(_) => return None,
+                Node::Leaf(Some(_)) if only.is_some() => return None,
+                Node::Leaf(Some(_)) => only = Some(i),
+                Node::Leaf(None) => {}
+            }
+        }
+
+        Some(match only {
+            Some(i) => std::mem::take(self.branches[i].as_mut()),
+            None => Node::default(),
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn oc_tree_base_work() {
@@ -315,4 +468,219 @@ mod tests {
             assert_eq!(gv, Some(v));
         }
     }
+
+    #[test]
+    fn oc_tree_remove() {
+        let instances: Vec<_> = (0..16)
+            .map(|i| {
+                Instance::new(
+                    Point3 {
+                        x: i,
+                        y: -i,
+                        z: i % 3,
+                    },
+                    i.to_string(),
+                )
+            })
+            .collect();
+
+        let mut octree = OcTree::from_scope(4);
+
+        for v in instances.clone() {
+            octree.insert(v);
+        }
+
+        assert_eq!(octree.remove(Point3 { x: 100, y: 0, z: 0 }), None);
+        assert_eq!(octree.len(), 16);
+
+        for (i, v) in instances.iter().enumerate() {
+            assert_eq!(octree.remove(v.pos()), Some(v.clone()));
+            assert_eq!(octree.remove(v.pos()), None);
+            assert_eq!(octree.get(v.pos()), None);
+            assert_eq!(octree.len(), 15 - i);
+        }
+
+        assert!(octree.is_empty());
+    }
+
+    #[test]
+    fn oc_tree_grow_toward() {
+        let mut octree = OcTree::from_scope(4);
+        let instances: Vec<_> = [0, -100, 37, -5, 250]
+            .into_iter()
+            .map(|i| {
+                Instance::new(
+                    Point3 {
+                        x: i,
+                        y: -i,
+                        z: i / 3,
+                    },
+                    i.to_string(),
+                )
+            })
+            .collect();
+
+        for v in instances.iter().cloned() {
+            octree.insert(v);
+        }
+
+        for v in instances.iter() {
+            assert_eq!(octree.get(v.pos()).as_ref(), Some(v));
+        }
+
+        assert_eq!(octree.len(), instances.len());
+        assert!(octree.scope() <= 512);
+    }
+
+    #[test]
+    fn oc_tree_re_root_far_away() {
+        let mut octree = OcTree::from_scope(4);
+        let far = Instance::new(
+            Point3 {
+                x: 5000,
+                y: 5000,
+                z: -5000,
+            },
+            "".to_owned(),
+        );
+
+        octree.insert(far.clone());
+
+        assert_eq!(octree.scope(), OcTree::from_scope(4).scope());
+        assert_eq!(octree.central(), far.pos());
+
+        let near = Instance::new(
+            Point3 {
+                x: 4997,
+                y: 5002,
+                z: -5000,
+            },
+            "".to_owned(),
+        );
+        octree.insert(near.clone());
+
+        assert_eq!(octree.get(far.pos()), Some(far));
+        assert_eq!(octree.get(near.pos()), Some(near));
+        assert_eq!(octree.scope(), OcTree::from_scope(4).scope());
+    }
+
+    #[test]
+    fn oc_tree_insert_replace() {
+        let mut octree = OcTree::from_scope(4);
+
+        for i in 0..10 {
+            octree.insert(Instance::new(
+                Point3 { x: i, y: 0, z: 0 },
+                "grass".to_owned(),
+            ));
+        }
+
+        for i in 0..10 {
+            let stone = Instance::new(Point3 { x: i, y: 0, z: 0 }, "stone".to_owned());
+
+            assert_eq!(
+                octree.insert(stone.clone()),
+                Some(Instance::new(stone.pos(), "grass".to_owned()))
+            );
+            assert_eq!(octree.get(stone.pos()), Some(stone));
+        }
+
+        assert_eq!(octree.len(), 10);
+        assert_eq!(octree.iter().count(), 10);
+    }
+
+    #[test]
+    fn oc_tree_remove_collapse() {
+        let a = Instance::new(Point3 { x: 1, y: 1, z: 1 }, "a".to_owned());
+        let b = Instance::new(Point3 { x: 2, y: 1, z: 1 }, "b".to_owned());
+
+        let mut octree = OcTree::from_scope(4);
+        octree.insert(a.clone());
+        octree.insert(b.clone());
+
+        octree.remove(b.pos());
+
+        let mut only_a = OcTree::from_scope(4);
+        only_a.insert(a.clone());
+
+        assert_eq!(octree, only_a);
+
+        octree.remove(a.pos());
+
+        assert_eq!(octree, OcTree::from_scope(4));
+    }
+
+    #[test]
+    fn oc_tree_from_scope() {
+        assert_eq!(OcTree::from_scope(0).scope(), 1);
+        assert_eq!(OcTree::from_scope(1).scope(), 1);
+        assert_eq!(OcTree::from_scope(3).scope(), 4);
+        assert_eq!(OcTree::from_scope(4).scope(), 4);
+        assert_eq!(OcTree::from_scope(5).scope(), 8);
+        assert_eq!(OcTree::default(), OcTree::from_scope(0));
+    }
+
+    #[test]
+    fn oc_tree_default_insert() {
+        let mut a = OcTree::default();
+
+        a.insert(Instance::default());
+        a.insert(Instance::new(Point3 { x: 9, y: -3, z: 1 }, "".to_owned()));
+
+        assert_eq!(a.len(), 2);
+        assert_eq!(
+            a.get(Point3 { x: 9, y: -3, z: 1 }).map(|v| v.pos()),
+            Some(Point3 { x: 9, y: -3, z: 1 })
+        );
+    }
+
+    fn arb_pos() -> impl Strategy<Value = Point3<i32>> {
+        (-300..300, -300..300, -300..300).prop_map(|(x, y, z)| Point3 { x, y, z })
+    }
+
+    proptest! {
+        #[test]
+        fn from_scope_power_of_two(scope in 0_usize..1 << 20) {
+            let a = OcTree::from_scope(scope);
+
+            prop_assert!(a.scope().is_power_of_two());
+            prop_assert!(a.scope() >= scope);
+            prop_assert!(a.scope() < scope.max(1) * 2);
+        }
+
+        #[test]
+        fn scope_bounds(scope in 0_usize..64) {
+            let a = OcTree::from_scope(scope);
+            let s = a.scope() as i32;
+
+            prop_assert!(a.is_in_scope(&Point3::new(-s, -s, -s)));
+            prop_assert!(a.is_in_scope(&Point3::new(s - 1, s - 1, s - 1)));
+            prop_assert!(!a.is_in_scope(&Point3::new(s, 0, 0)));
+            prop_assert!(!a.is_in_scope(&Point3::new(0, -s - 1, 0)));
+        }
+
+        #[test]
+        fn scope_growth(scope in 0_usize..16, positions in prop::collection::vec(arb_pos(), 1..64)) {
+            let mut a = OcTree::from_scope(scope);
+
+            for (i, pos) in positions.iter().enumerate() {
+                a.insert(Instance::new(*pos, i.to_string()));
+
+                prop_assert!(a.scope().is_power_of_two());
+                prop_assert!(a.is_in_scope(pos));
+            }
+
+            let mut expect = std::collections::HashMap::new();
+            for (i, pos) in positions.iter().enumerate() {
+                expect.insert(*pos, i.to_string());
+            }
+
+            prop_assert_eq!(a.len(), expect.len());
+            prop_assert_eq!(a.iter().count(), expect.len());
+
+            for (pos, style_id) in expect {
+                prop_assert_eq!(a.get(pos), Some(Instance::new(pos, style_id)));
+            }
+        }
+    }
 }
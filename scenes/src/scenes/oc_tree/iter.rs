@@ -1,110 +1,140 @@
-use cgmath::Point3;
+use super::{Node, OcTree};
 use crate::scenes::instance::Instance;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Depth-first walk through the occupied branches of an OcTree
+#[derive(Debug, Clone)]
 pub struct RefIter<'a> {
-    oc_tree: &'a super::OcTree,
-    counter: AabbCounter,
+    stack: Vec<&'a Node>,
+    len: usize,
 }
 
 impl<'a> RefIter<'a> {
-    pub(super) fn new(oc_tree: &'a super::OcTree) -> Self {
+    pub(super) fn new(oc_tree: &'a OcTree) -> Self {
         Self {
-            counter: AabbCounter::new(oc_tree.__scope__),
-            oc_tree,
+            stack: vec![&oc_tree.__value__],
+            len: oc_tree.__len__,
         }
     }
 }
 
 impl<'a> Iterator for RefIter<'a> {
-    type Item = Instance;
+    type Item = &'a Instance;
 
     fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            if let Some(pos) = self.counter.next() {
-                if let Some(ans) = self.oc_tree.get(pos) {
-                    return Some(ans);
-                } else {
-                    continue;
+        while let Some(node) = self.stack.pop() {
+            match node {
+                Node::Trunk(t) => self
+                    .stack
+                    .extend(t.branches.iter().rev().map(|b| b.as_ref())),
+                Node::Leaf(Some(v)) => {
+                    self.len -= 1;
+                    return Some(v);
                 }
-            } else {
-                return None;
+                Node::Leaf(None) => {}
             }
         }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
     }
 }
 
-/// pos iterator for 3d AABB-box
-/// # Example
-/// ```
-/// # use cgmath::Point3;
-/// let mut ac = AabbCounter::new(1);
-///
-/// assert_eq!(ac.next(), Some(Point3 { x: -1, y: -1, z: -1 }));
-/// assert_eq!(ac.next(), Some(Point3 { x: 0, y: -1, z: -1 }));
-/// assert_eq!(ac.next(), Some(Point3 { x: -1, y: 0, z: -1 }));
-/// assert_eq!(ac.next(), Some(Point3 { x: 0, y: 0, z: -1 }));
-/// assert_eq!(ac.next(), Some(Point3 { x: -1, y: -1, z: 0 }));
-/// assert_eq!(ac.next(), Some(Point3 { x: 0, y: -1, z: 0 }));
-/// assert_eq!(ac.next(), Some(Point3 { x: -1, y: 0, z: 0 }));
-/// assert_eq!(ac.next(), Some(Point3 { x: 0, y: 0, z: 0 }));
-/// assert_eq!(ac.next(), None);
-/// ```
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub(super) struct AabbCounter {
-    scope: usize,
-    pos: Point3<i32>,
+impl<'a> ExactSizeIterator for RefIter<'a> {}
+
+/// Instances of an OcTree sorted as x++ -overflow-> y++ -overflow-> z++
+#[derive(Debug, Clone)]
+pub struct OrderedIter<'a> {
+    sorted: std::vec::IntoIter<&'a Instance>,
 }
 
-impl AabbCounter {
-    pub(super) fn new(scope: usize) -> Self {
+impl<'a> OrderedIter<'a> {
+    pub(super) fn new(oc_tree: &'a OcTree) -> Self {
+        let mut sorted: Vec<_> = oc_tree.iter().collect();
+        sorted.sort_unstable_by_key(|v| {
+            let pos = v.pos();
+            (pos.z, pos.y, pos.x)
+        });
+
         Self {
-            scope,
-            pos: Point3 {
-                x: -(scope as i32) - 1,
-                y: -(scope as i32),
-                z: -(scope as i32),
-            },
+            sorted: sorted.into_iter(),
         }
     }
+}
 
-    pub(super) fn scope(&self) -> usize {
-        self.scope
+impl<'a> Iterator for OrderedIter<'a> {
+    type Item = &'a Instance;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.sorted.next()
     }
 
-    pub(super) fn pos(&self) -> Point3<i32> {
-        self.pos
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.sorted.size_hint()
     }
 }
 
-impl Default for AabbCounter {
-    fn default() -> Self {
-        Self {
-            scope: 0,
-            pos: Point3 { x: 0, y: 0, z: 0 },
+impl<'a> ExactSizeIterator for OrderedIter<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Point3;
+
+    #[test]
+    fn ref_iter_sparse() {
+        let instances: Vec<_> = [-1000, -3, 0, 7, 900]
+            .into_iter()
+            .map(|i| {
+                Instance::new(
+                    Point3 {
+                        x: i,
+                        y: -i,
+                        z: i / 2,
+                    },
+                    i.to_string(),
+                )
+            })
+            .collect();
+
+        let mut octree = OcTree::from_scope(1);
+
+        for v in instances.iter().cloned() {
+            octree.insert(v);
         }
-    }
-}
 
-impl Iterator for AabbCounter {
-    type Item = Point3<i32>;
+        let mut got: Vec<_> = octree.iter().cloned().collect();
+        got.sort_by_key(|v| v.pos().x);
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let mut pos = Point3::new(self.pos.x + 1, self.pos.y, self.pos.z);
+        assert_eq!(octree.iter().len(), instances.len());
+        assert_eq!(got, instances);
+    }
+
+    #[test]
+    fn ordered_iter() {
+        let mut octree = OcTree::from_scope(4);
 
-        if pos.x >= self.scope as i32 {
-            pos = Point3::new(-(self.scope as i32), self.pos.y + 1, self.pos.z)
-        };
+        for z in (-2..2).rev() {
+            for x in -2..2 {
+                for y in (-2..2).rev() {
+                    octree.insert(Instance::new(Point3 { x, y, z }, "".to_owned()));
+                }
+            }
+        }
 
-        if pos.y >= self.scope as i32 {
-            pos = Point3::new(-(self.scope as i32), -(self.scope as i32), self.pos.z + 1)
-        };
+        let got: Vec<_> = octree.iter_ordered().map(|v| v.pos()).collect();
 
-        if pos.z >= self.scope as i32 {
-            return None;
-        };
+        let mut expect = Vec::new();
+        for z in -2..2 {
+            for y in -2..2 {
+                for x in -2..2 {
+                    expect.push(Point3 { x, y, z });
+                }
+            }
+        }
 
-        self.pos = pos;
-        Some(pos)
+        assert_eq!(got, expect);
     }
 }
@@ -0,0 +1,599 @@
+use super::{Node, OcTree};
+use crate::scenes::instance::Instance;
+use cgmath::{InnerSpace, Point3, Vector3};
+
+impl OcTree {
+    /// all the instances inside the AABB-Box, `min` and `max` included
+    ///
+    /// # Example
+    /// ```
+    /// # use scenes::{Instance, OcTree};
+    /// # use cgmath::Point3;
+    /// let mut a = OcTree::from_scope(4);
+    ///
+    /// for x in 0..10 {
+    ///     a.insert(Instance::new(Point3 { x, y: 0, z: 0 }, "".to_owned()));
+    /// }
+    ///
+    /// let got = a.query_aabb(Point3 { x: 2, y: -1, z: -1 }, Point3 { x: 4, y: 1, z: 1 });
+    ///
+    /// assert_eq!(got.len(), 3);
+    /// ```
+    pub fn query_aabb(&self, min: Point3<i32>, max: Point3<i32>) -> Vec<&Instance> {
+        let mut ans = Vec::new();
+
+        collect(
+            &self.__value__,
+            Bounds::of_root(self),
+            &|b: &Bounds| {
+                b.min.x <= max.x
+                    && b.max.x > min.x
+                    && b.min.y <= max.y
+                    && b.max.y > min.y
+                    && b.min.z <= max.z
+                    && b.max.z > min.z
+            },
+            &|v: &Instance| {
+                let p = v.pos();
+                p.x >= min.x
+                    && p.x <= max.x
+                    && p.y >= min.y
+                    && p.y <= max.y
+                    && p.z >= min.z
+                    && p.z <= max.z
+            },
+            &mut ans,
+        );
+
+        ans
+    }
+
+    /// all the instances whose voxel centre is inside the sphere
+    ///
+    /// A voxel at `pos` fills `pos..pos + 1`, its centre is `pos + 0.5`.
+    ///
+    /// # Example
+    /// ```
+    /// # use scenes::{Instance, OcTree};
+    /// # use cgmath::Point3;
+    /// let mut a = OcTree::from_scope(4);
+    ///
+    /// for x in 0..10 {
+    ///     a.insert(Instance::new(Point3 { x, y: 0, z: 0 }, "".to_owned()));
+    /// }
+    ///
+    /// let got = a.query_sphere(Point3 { x: 0.5, y: 0.5, z: 0.5 }, 2.0);
+    ///
+    /// assert_eq!(got.len(), 3);
+    /// ```
+    pub fn query_sphere(&self, central: Point3<f32>, radius: f32) -> Vec<&Instance> {
+        let mut ans = Vec::new();
+
+        collect(
+            &self.__value__,
+            Bounds::of_root(self),
+            &|b: &Bounds| {
+                let (min, max) = b.to_f32();
+                let closest = Point3::new(
+                    central.x.clamp(min.x, max.x),
+                    central.y.clamp(min.y, max.y),
+                    central.z.clamp(min.z, max.z),
+                );
+
+                (closest - central).magnitude2() <= radius * radius
+            },
+            &|v: &Instance| (voxel_central(v.pos()) - central).magnitude2() <= radius * radius,
+            &mut ans,
+        );
+
+        ans
+    }
+
+    /// the first instance hit by the ray, no further than `max_distance`
+    ///
+    /// # Example
+    /// ```
+    /// # use scenes::{Instance, OcTree};
+    /// # use cgmath::{Point3, Vector3};
+    /// let mut a = OcTree::from_scope(4);
+    ///
+    /// a.insert(Instance::new(Point3 { x: 0, y: 0, z: 0 }, "floor".to_owned()));
+    ///
+    /// let hit = a
+    ///     .raycast(Point3 { x: 0.5, y: 0.5, z: 10.0 }, Vector3 { x: 0.0, y: 0.0, z: -1.0 }, 100.0)
+    ///     .unwrap();
+    ///
+    /// assert_eq!(hit.instance().style_id(), "floor");
+    /// assert_eq!(hit.distance(), 9.0);
+    /// assert_eq!(hit.normal(), Vector3 { x: 0, y: 0, z: 1 });
+    /// ```
+    pub fn raycast(
+        &self,
+        origin: Point3<f32>,
+        direction: Vector3<f32>,
+        max_distance: f32,
+    ) -> Option<RayHit<'_>> {
+        if direction.magnitude2() == 0.0 {
+            return None;
+        }
+
+        let ray = Ray {
+            origin,
+            direction: direction.normalize(),
+            max_distance,
+        };
+        let mut ans = None;
+
+        ray.cast(&self.__value__, Bounds::of_root(self), &mut ans);
+
+        ans
+    }
+}
+
+impl OcTree {
+    /// remove every instance in the AABB-Box from `min` to `max`, both included,
+    /// in one walk, branches left with one or zero Instance collapse on the way back
+    ///
+    /// # Example
+    /// ```
+    /// # use scenes::{Instance, OcTree};
+    /// # use cgmath::Point3;
+    /// let mut a = OcTree::from_scope(8);
+    /// for x in -4..4 {
+    ///     a.insert(Instance::new(Point3 { x, y: 0, z: 0 }, "".to_owned()));
+    /// }
+    ///
+    /// let removed = a.remove_aabb(Point3 { x: -1, y: 0, z: 0 }, Point3 { x: 2, y: 0, z: 0 });
+    ///
+    /// assert_eq!(removed.len(), 4);
+    /// assert_eq!(a.len(), 4);
+    /// ```
+    pub fn remove_aabb(&mut self, min: Point3<i32>, max: Point3<i32>) -> Vec<Instance> {
+        let mut ans = Vec::new();
+        let bounds = Bounds::of_root(self);
+
+        take(&mut self.__value__, bounds, min, max, &mut ans);
+        self.__len__ -= ans.len();
+
+        ans
+    }
+}
+
+/// The instance first hit by a ray
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit<'a> {
+    instance: &'a Instance,
+    distance: f32,
+    normal: Vector3<i32>,
+}
+
+impl<'a> RayHit<'a> {
+    pub fn instance(&self) -> &'a Instance {
+        self.instance
+    }
+
+    /// distance from the ray origin to the hit point
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    /// normal of the face the ray enter the voxel,
+    /// zero if the ray start inside the voxel
+    pub fn normal(&self) -> Vector3<i32> {
+        self.normal
+    }
+}
+
+/// AABB-Box of a node, as `min..max` on every axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Bounds {
+    min: Point3<i32>,
+    max: Point3<i32>,
+}
+
+impl Bounds {
+    fn of_root(oc_tree: &OcTree) -> Self {
+        let scope = oc_tree.__scope__ as i32;
+        let offset = Vector3::new(scope, scope, scope);
+
+        Self {
+            min: oc_tree.__central__ - offset,
+            max: oc_tree.__central__ + offset,
+        }
+    }
+
+    /// bounds of the branch at `toward`, of a Trunk at `central`
+    fn branch(&self, central: Point3<i32>, toward: usize) -> Self {
+        let mut ans = *self;
+
+        if toward & 1 != 0 {
+            ans.min.x = central.x
+        } else {
+            ans.max.x = central.x
+        };
+
+        if toward & 2 != 0 {
+            ans.min.y = central.y
+        } else {
+            ans.max.y = central.y
+        };
+
+        if toward & 4 != 0 {
+            ans.min.z = central.z
+        } else {
+            ans.max.z = central.z
+        };
+
+        ans
+    }
+
+    fn to_f32(self) -> (Point3<f32>, Point3<f32>) {
+        (self.min.cast().unwrap(), self.max.cast().unwrap())
+    }
+}
+
+fn voxel_central(pos: Point3<i32>) -> Point3<f32> {
+    pos.cast::<f32>().unwrap() + Vector3::new(0.5, 0.5, 0.5)
+}
+
+/// walk down the branches overlapping the query,
+/// push every instance the query contains
+fn collect<'a>(
+    node: &'a Node,
+    bounds: Bounds,
+    overlap: &impl Fn(&Bounds) -> bool,
+    contains: &impl Fn(&Instance) -> bool,
+    ans: &mut Vec<&'a Instance>,
+) {
+    match node {
+        Node::Trunk(t) => {
+            for (toward, branch) in t.branches.iter().enumerate() {
+                let bounds = bounds.branch(t.central, toward);
+
+                if overlap(&bounds) {
+                    collect(branch, bounds, overlap, contains, ans);
+                }
+            }
+        }
+        Node::Leaf(Some(v)) if contains(v) => ans.push(v),
+        Node::Leaf(_) => {}
+    }
+}
+
+/// walk down the branches overlapping `min..=max`, take every instance in it
+fn take(
+    node: &mut Node,
+    bounds: Bounds,
+    min: Point3<i32>,
+    max: Point3<i32>,
+    ans: &mut Vec<Instance>,
+) {
+    match node {
+        Node::Trunk(t) => {
+            let before = ans.len();
+
+            for (toward, branch) in t.branches.iter_mut().enumerate() {
+                let b = bounds.branch(t.central, toward);
+
+                if b.min.x <= max.x
+                    && b.max.x > min.x
+                    && b.min.y <= max.y
+                    && b.max.y > min.y
+                    && b.min.z <= max.z
+                    && b.max.z > min.z
+                {
+                    take(branch, b, min, max, ans);
+                }
+            }
+
+            if ans.len() > before {
+                if let Some(leaf) = t.collapse() {
+                    *node = leaf;
+                }
+            }
+        }
+        Node::Leaf(v) => {
+            let inside = v.as_ref().is_some_and(|v| {
+                let p = v.pos();
+                (min.x..=max.x).contains(&p.x)
+                    && (min.y..=max.y).contains(&p.y)
+                    && (min.z..=max.z).contains(&p.z)
+            });
+
+            if inside {
+                ans.extend(v.take());
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Ray {
+    origin: Point3<f32>,
+    /// normalized
+    direction: Vector3<f32>,
+    max_distance: f32,
+}
+
+impl Ray {
+    /// distance the ray enter the box and the axis of the face it enter,
+    /// no axis if the origin is inside the box
+    fn enter(&self, min: Point3<f32>, max: Point3<f32>) -> Option<(f32, Option<usize>)> {
+        let mut near = (f32::NEG_INFINITY, None);
+        let mut far = f32::INFINITY;
+
+        for axis in 0..3 {
+            let (o, d) = (self.origin[axis], self.direction[axis]);
+
+            if d == 0.0 {
+                if o < min[axis] || o > max[axis] {
+                    return None;
+                }
+                continue;
+            }
+
+            let t1 = (min[axis] - o) / d;
+            let t2 = (max[axis] - o) / d;
+
+            if t1.min(t2) > near.0 {
+                near = (t1.min(t2), Some(axis));
+            }
+            far = far.min(t1.max(t2));
+        }
+
+        if near.0 > far || far < 0.0 {
+            None
+        } else if near.0 < 0.0 {
+            Some((0.0, None))
+        } else {
+            Some(near)
+        }
+    }
+
+    fn cast<'a>(&self, node: &'a Node, bounds: Bounds, ans: &mut Option<RayHit<'a>>) {
+        let nearest = ans.map_or(self.max_distance, |h| h.distance);
+
+        match node {
+            Node::Trunk(t) => {
+                let mut branches: Vec<_> = t
+                    .branches
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, b)| !matches!(b.as_ref(), Node::Leaf(None)))
+                    .filter_map(|(toward, b)| {
+                        let bounds = bounds.branch(t.central, toward);
+                        let (min, max) = bounds.to_f32();
+
+                        self.enter(min, max)
+                            .filter(|(d, _)| *d <= nearest)
+                            .map(|(d, _)| (d, b, bounds))
+                    })
+                    .collect();
+
+                branches.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+                for (d, branch, bounds) in branches {
+                    if ans.is_some_and(|h| h.distance < d) {
+                        break;
+                    }
+
+                    self.cast(branch, bounds, ans);
+                }
+            }
+            Node::Leaf(Some(v)) => {
+                let min = v.pos().cast::<f32>().unwrap();
+                let max = min + Vector3::new(1.0, 1.0, 1.0);
+
+                if let Some((distance, axis)) = self.enter(min, max) {
+                    if distance <= nearest && ans.map_or(true, |h| distance < h.distance) {
+                        let mut normal = Vector3::new(0, 0, 0);
+
+                        if let Some(axis) = axis {
+                            normal[axis] = if self.direction[axis] > 0.0 { -1 } else { 1 };
+                        }
+
+                        *ans = Some(RayHit {
+                            instance: v,
+                            distance,
+                            normal,
+                        });
+                    }
+                }
+            }
+            Node::Leaf(None) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn octree() -> OcTree {
+        let mut octree = OcTree::from_scope(4);
+
+        for x in -20..20 {
+            for y in -20..20 {
+                octree.insert(Instance::new(Point3 { x, y, z: 0 }, "floor".to_owned()));
+            }
+        }
+
+        octree.insert(Instance::new(
+            Point3 { x: 3, y: 3, z: 1 },
+            "wall".to_owned(),
+        ));
+        octree.insert(Instance::new(
+            Point3 { x: 3, y: 3, z: 2 },
+            "wall".to_owned(),
+        ));
+
+        octree
+    }
+
+    #[test]
+    fn query_aabb_match_scan() {
+        let octree = octree();
+        let (min, max) = (Point3 { x: -3, y: 1, z: 0 }, Point3 { x: 5, y: 3, z: 5 });
+
+        let mut got: Vec<_> = octree
+            .query_aabb(min, max)
+            .into_iter()
+            .map(|v| v.pos())
+            .collect();
+        let mut expect: Vec<_> = octree
+            .iter()
+            .map(|v| v.pos())
+            .filter(|p| (min.x..=max.x).contains(&p.x))
+            .filter(|p| (min.y..=max.y).contains(&p.y))
+            .filter(|p| (min.z..=max.z).contains(&p.z))
+            .collect();
+
+        got.sort_by_key(|p| (p.x, p.y, p.z));
+        expect.sort_by_key(|p| (p.x, p.y, p.z));
+
+        assert_eq!(got.len(), 9 * 3 + 2);
+        assert_eq!(got, expect);
+    }
+
+    #[test]
+    fn remove_aabb_match_remove() {
+        let mut batch = octree();
+        let mut one_by_one = octree();
+        let (min, max) = (Point3 { x: -3, y: 1, z: 0 }, Point3 { x: 5, y: 3, z: 5 });
+
+        let expect: Vec<_> = batch.query_aabb(min, max).into_iter().cloned().collect();
+        let got = batch.remove_aabb(min, max);
+        for v in &expect {
+            one_by_one.remove(v.pos());
+        }
+
+        assert_eq!(got, expect);
+        assert_eq!(batch.len(), 40 * 40 + 2 - got.len());
+        // collapsed as removing one by one does
+        assert_eq!(batch, one_by_one);
+
+        let all = batch.remove_aabb(
+            Point3 {
+                x: -99,
+                y: -99,
+                z: -99,
+            },
+            Point3 {
+                x: 99,
+                y: 99,
+                z: 99,
+            },
+        );
+        assert_eq!(all.len(), 40 * 40 + 2 - got.len());
+        assert_eq!(batch, OcTree::with_central(batch.central(), batch.scope()));
+    }
+
+    #[test]
+    fn query_sphere_match_scan() {
+        let octree = octree();
+        let central = Point3 {
+            x: 2.0,
+            y: 2.5,
+            z: 1.0,
+        };
+
+        let mut got: Vec<_> = octree
+            .query_sphere(central, 3.0)
+            .into_iter()
+            .map(|v| v.pos())
+            .collect();
+        let mut expect: Vec<_> = octree
+            .iter()
+            .map(|v| v.pos())
+            .filter(|p| (voxel_central(*p) - central).magnitude() <= 3.0)
+            .collect();
+
+        got.sort_by_key(|p| (p.x, p.y, p.z));
+        expect.sort_by_key(|p| (p.x, p.y, p.z));
+
+        assert!(!got.is_empty());
+        assert_eq!(got, expect);
+    }
+
+    #[test]
+    fn raycast_first_hit() {
+        let octree = octree();
+
+        let hit = octree
+            .raycast(
+                Point3 {
+                    x: -5.5,
+                    y: 3.5,
+                    z: 2.5,
+                },
+                Vector3 {
+                    x: 1.0,
+                    y: 0.0,
+                    z: 0.0,
+                },
+                100.0,
+            )
+            .unwrap();
+
+        assert_eq!(hit.instance().pos(), Point3 { x: 3, y: 3, z: 2 });
+        assert_eq!(hit.distance(), 8.5);
+        assert_eq!(hit.normal(), Vector3 { x: -1, y: 0, z: 0 });
+
+        let hit = octree
+            .raycast(
+                Point3 {
+                    x: -3.5,
+                    y: -3.5,
+                    z: 10.0,
+                },
+                Vector3 {
+                    x: 1.0,
+                    y: 1.0,
+                    z: -1.0,
+                },
+                100.0,
+            )
+            .unwrap();
+
+        assert_eq!(hit.instance().pos(), Point3 { x: 3, y: 3, z: 2 });
+        assert_eq!(hit.normal(), Vector3 { x: 0, y: 0, z: 1 });
+    }
+
+    #[test]
+    fn raycast_miss() {
+        let octree = octree();
+
+        assert_eq!(
+            octree.raycast(
+                Point3 {
+                    x: 0.5,
+                    y: 0.5,
+                    z: 10.0
+                },
+                Vector3 {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 1.0
+                },
+                100.0
+            ),
+            None
+        );
+        assert_eq!(
+            octree.raycast(
+                Point3 {
+                    x: 0.5,
+                    y: 0.5,
+                    z: 10.0
+                },
+                Vector3 {
+                    x: 0.0,
+                    y: 0.0,
+                    z: -1.0
+                },
+                5.0
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,299 @@
+use super::{history::History, instance::Instance, Scene};
+use cgmath::{Point3, Vector3};
+use std::collections::BTreeSet;
+
+/// Horizontal axis a [`Selection`] mirrors across
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+}
+
+/// A set of positions on a map, transformed as a block
+///
+/// Every transform is applied through the history as one undo step,
+/// taking all the instances out before putting any back,
+/// so a block moved onto itself never overwrites its own tiles.
+/// The selection follows the instances to where they land,
+/// positions with no Instance are dropped.
+///
+/// # Example
+/// ```
+/// # use scenes::{History, Instance, Scene, Selection};
+/// # use cgmath::{Point3, Vector3};
+/// let mut scene = Scene::new();
+/// let mut history = History::default();
+/// for x in 0..3 {
+///     scene.insert(Instance::new(Point3::new(x, 0, 0), "wall"));
+/// }
+///
+/// let mut selection = Selection::new();
+/// selection.select_aabb(&scene, Point3::new(0, 0, 0), Point3::new(2, 0, 0));
+///
+/// // a row along x turned a quarter, a row along y
+/// selection.rotate(&mut scene, &mut history, 1);
+/// assert!(scene.iter().all(|v| v.pos().x == 1));
+///
+/// history.undo(&mut scene);
+/// assert!(scene.iter().all(|v| v.pos().y == 0));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selection {
+    /// as (x, y, z), for an order
+    positions: BTreeSet<(i32, i32, i32)>,
+}
+
+impl Selection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// false if already selected
+    pub fn insert(&mut self, pos: Point3<i32>) -> bool {
+        self.positions.insert(key(pos))
+    }
+
+    /// false if not selected
+    pub fn remove(&mut self, pos: Point3<i32>) -> bool {
+        self.positions.remove(&key(pos))
+    }
+
+    /// select if not, unselect if so
+    pub fn toggle(&mut self, pos: Point3<i32>) {
+        if !self.remove(pos) {
+            self.insert(pos);
+        }
+    }
+
+    /// select every Instance from `min` to `max`, both included
+    pub fn select_aabb(&mut self, scene: &Scene, min: Point3<i32>, max: Point3<i32>) {
+        for v in scene.oc_tree().query_aabb(min, max) {
+            self.insert(v.pos());
+        }
+    }
+
+    pub fn contains(&self, pos: Point3<i32>) -> bool {
+        self.positions.contains(&key(pos))
+    }
+
+    /// in x, y then z order
+    pub fn iter(&self) -> impl Iterator<Item = Point3<i32>> + '_ {
+        self.positions.iter().map(|&(x, y, z)| Point3::new(x, y, z))
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.positions.clear();
+    }
+
+    /// smallest and largest corner of the box around it, both included
+    pub fn bounds(&self) -> Option<(Point3<i32>, Point3<i32>)> {
+        let mut iter = self.iter();
+        let first = iter.next()?;
+
+        Some(iter.fold((first, first), |(min, max), p| {
+            (
+                Point3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+                Point3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+            )
+        }))
+    }
+
+    /// move by offset, overwriting what's there
+    pub fn translate(&mut self, scene: &mut Scene, history: &mut History, offset: Vector3<i32>) {
+        self.apply(scene, history, false, |p| p + offset);
+    }
+
+    /// Turn counter-clockwise seen from above by quarter turns,
+    /// around the vertical axis through the center of the bounds
+    pub fn rotate(&mut self, scene: &mut Scene, history: &mut History, quarter_turns: i32) {
+        let (min, max) = match self.bounds() {
+            Some(b) => b,
+            None => return,
+        };
+        // twice the coordinates, so the centers of cells and bounds are integers
+        let (cx, cy) = (min.x + max.x + 1, min.y + max.y + 1);
+
+        self.apply(scene, history, false, |p| {
+            let (mut x, mut y) = (2 * p.x + 1 - cx, 2 * p.y + 1 - cy);
+            for _ in 0..quarter_turns.rem_euclid(4) {
+                (x, y) = (-y, x);
+            }
+
+            Point3::new((x + cx - 1).div_euclid(2), (y + cy - 1).div_euclid(2), p.z)
+        });
+    }
+
+    /// flip across the middle of the bounds, along the axis
+    pub fn mirror(&mut self, scene: &mut Scene, history: &mut History, axis: Axis) {
+        let (min, max) = match self.bounds() {
+            Some(b) => b,
+            None => return,
+        };
+
+        self.apply(scene, history, false, |p| match axis {
+            Axis::X => Point3::new(min.x + max.x - p.x, p.y, p.z),
+            Axis::Y => Point3::new(p.x, min.y + max.y - p.y, p.z),
+        });
+    }
+
+    /// copy to offset, then select the copies
+    pub fn duplicate(&mut self, scene: &mut Scene, history: &mut History, offset: Vector3<i32>) {
+        self.apply(scene, history, true, |p| p + offset);
+    }
+
+    /// remove every Instance selected, then the selection is empty
+    pub fn delete(&mut self, scene: &mut Scene, history: &mut History) {
+        history.remove_all(scene, self.iter());
+
+        self.clear();
+    }
+
+    /// put every Instance selected at `to` of its position, as one step,
+    /// leaving the originals if `keep`
+    fn apply(
+        &mut self,
+        scene: &mut Scene,
+        history: &mut History,
+        keep: bool,
+        to: impl Fn(Point3<i32>) -> Point3<i32>,
+    ) {
+        let instances: Vec<_> = self.iter().filter_map(|p| scene.get(p)).collect();
+
+        history.begin_group();
+        if !keep {
+            for v in &instances {
+                history.remove(scene, v.pos());
+            }
+        }
+        for v in &instances {
+            history.insert(scene, Instance::new(to(v.pos()), v.style_id().clone()));
+        }
+        history.end_group();
+
+        self.positions = instances.iter().map(|v| key(to(v.pos()))).collect();
+    }
+}
+
+fn key(p: Point3<i32>) -> (i32, i32, i32) {
+    (p.x, p.y, p.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// an L of three, and a tile apart
+    fn scene() -> Scene {
+        let mut scene = Scene::new();
+        for (x, y, style) in [(0, 0, "a"), (1, 0, "b"), (0, 1, "c"), (5, 5, "d")] {
+            scene.insert(Instance::new(Point3::new(x, y, 0), style));
+        }
+        scene
+    }
+
+    fn l_shape(scene: &Scene) -> Selection {
+        let mut ans = Selection::new();
+        ans.select_aabb(scene, Point3::new(0, 0, 0), Point3::new(1, 1, 0));
+        ans
+    }
+
+    fn style_at(scene: &Scene, x: i32, y: i32) -> Option<String> {
+        scene
+            .get(Point3::new(x, y, 0))
+            .map(|v| v.style_id().to_string())
+    }
+
+    #[test]
+    fn translate_overlapping_itself() {
+        let (mut scene, mut history) = (scene(), History::default());
+        let mut selection = l_shape(&scene);
+
+        selection.translate(&mut scene, &mut history, Vector3::new(1, 0, 0));
+
+        assert_eq!(scene.len(), 4);
+        assert_eq!(style_at(&scene, 1, 0).as_deref(), Some("a"));
+        assert_eq!(style_at(&scene, 2, 0).as_deref(), Some("b"));
+        assert_eq!(style_at(&scene, 1, 1).as_deref(), Some("c"));
+        assert!(selection.contains(Point3::new(2, 0, 0)));
+
+        assert!(history.undo(&mut scene));
+        assert_eq!(scene, self::scene());
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn rotate_full_turn() {
+        let (mut scene, mut history) = (scene(), History::default());
+        let mut selection = l_shape(&scene);
+
+        selection.rotate(&mut scene, &mut history, 1);
+        // (0,0) -> (1,0), (1,0) -> (1,1), (0,1) -> (0,0), in 2x2 bounds
+        assert_eq!(style_at(&scene, 1, 0).as_deref(), Some("a"));
+        assert_eq!(style_at(&scene, 1, 1).as_deref(), Some("b"));
+        assert_eq!(style_at(&scene, 0, 0).as_deref(), Some("c"));
+
+        selection.rotate(&mut scene, &mut history, 3);
+        assert_eq!(scene, self::scene());
+
+        // an odd row turns around its middle cell
+        let mut selection = Selection::new();
+        for x in 0..3 {
+            scene.insert(Instance::new(Point3::new(x, 9, 0), "row"));
+            selection.insert(Point3::new(x, 9, 0));
+        }
+        selection.rotate(&mut scene, &mut history, -1);
+        assert_eq!(
+            selection.iter().collect::<Vec<_>>(),
+            [
+                Point3::new(1, 8, 0),
+                Point3::new(1, 9, 0),
+                Point3::new(1, 10, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn mirror_duplicate_delete() {
+        let (mut scene, mut history) = (scene(), History::default());
+        let mut selection = l_shape(&scene);
+
+        selection.mirror(&mut scene, &mut history, Axis::X);
+        assert_eq!(style_at(&scene, 1, 0).as_deref(), Some("a"));
+        assert_eq!(style_at(&scene, 0, 0).as_deref(), Some("b"));
+        assert_eq!(style_at(&scene, 1, 1).as_deref(), Some("c"));
+
+        selection.duplicate(&mut scene, &mut history, Vector3::new(0, 0, 1));
+        assert_eq!(scene.len(), 7);
+        assert!(selection.iter().all(|p| p.z == 1));
+
+        selection.delete(&mut scene, &mut history);
+        assert_eq!(scene.len(), 4);
+        assert!(selection.is_empty());
+
+        for _ in 0..3 {
+            assert!(history.undo(&mut scene));
+        }
+        assert_eq!(scene, self::scene());
+    }
+
+    #[test]
+    fn empty_positions_dropped() {
+        let (mut scene, mut history) = (scene(), History::default());
+        let mut selection = Selection::new();
+        selection.insert(Point3::new(5, 5, 0));
+        selection.insert(Point3::new(7, 7, 7));
+
+        selection.translate(&mut scene, &mut history, Vector3::new(0, 1, 0));
+
+        assert_eq!(selection.len(), 1);
+        assert_eq!(style_at(&scene, 5, 6).as_deref(), Some("d"));
+    }
+}
@@ -0,0 +1,343 @@
+use super::{instance::Instance, Scene};
+use cgmath::Point3;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Borrow, collections::HashMap, fmt::Display, ops::Deref, sync::Arc};
+
+/// Id of a Style, shared by every Instance using it
+///
+/// Cloning only bumps a reference count,
+/// ids from the same [`StyleRegistry`] share one heap string.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StyleId(Arc<str>);
+
+impl StyleId {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for StyleId {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Borrow<str> for StyleId {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for StyleId {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for StyleId {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl Display for StyleId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<&str> for StyleId {
+    fn from(s: &str) -> Self {
+        Self(Arc::from(s))
+    }
+}
+
+impl From<String> for StyleId {
+    fn from(s: String) -> Self {
+        Self(Arc::from(s))
+    }
+}
+
+/// Definition of a tile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Style {
+    model: String,
+    texture: Option<String>,
+    tint: [u8; 4],
+    passable: bool,
+    light: f32,
+}
+
+impl Style {
+    /// a solid, untinted and unlit tile
+    ///
+    /// `model` is the name to request as a model, as "cube.obj"
+    pub fn new(model: String) -> Self {
+        Self {
+            model,
+            texture: None,
+            tint: [255, 255, 255, 255],
+            passable: false,
+            light: 0.0,
+        }
+    }
+
+    /// `texture` is the name to request as a image, as "grass.png"
+    pub fn with_texture(mut self, texture: String) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    /// rgba, multiplied onto the texture
+    pub fn with_tint(mut self, tint: [u8; 4]) -> Self {
+        self.tint = tint;
+        self
+    }
+
+    pub fn with_passable(mut self, passable: bool) -> Self {
+        self.passable = passable;
+        self
+    }
+
+    /// radius of the light emitted, in tiles
+    pub fn with_light(mut self, light: f32) -> Self {
+        self.light = light;
+        self
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub fn texture(&self) -> Option<&str> {
+        self.texture.as_deref()
+    }
+
+    pub fn tint(&self) -> [u8; 4] {
+        self.tint
+    }
+
+    /// could tokens walk through it
+    pub fn passable(&self) -> bool {
+        self.passable
+    }
+
+    /// radius of the light emitted, in tiles, 0 for no light
+    pub fn light(&self) -> f32 {
+        self.light
+    }
+}
+
+/// All the styles a map could use
+///
+/// # Example
+/// ```
+/// # use scenes::{Instance, Scene, Style, StyleRegistry};
+/// # use cgmath::Point3;
+/// let mut registry = StyleRegistry::new();
+/// let grass = registry.register("grass", Style::new("cube.obj".to_owned()));
+///
+/// let mut scene = Scene::new();
+/// scene.insert(Instance::new(Point3 { x: 0, y: 0, z: 0 }, grass));
+///
+/// assert!(registry.validate(&scene).is_ok());
+///
+/// scene.insert(Instance::new(Point3 { x: 1, y: 0, z: 0 }, "lava"));
+///
+/// assert_eq!(registry.validate(&scene).unwrap_err().instances().len(), 1);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StyleRegistry {
+    styles: HashMap<StyleId, Style>,
+}
+
+impl StyleRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// define or redefine a style, return its shared id
+    pub fn register(&mut self, id: &str, style: Style) -> StyleId {
+        let id = self.intern(id).unwrap_or_else(|| StyleId::from(id));
+
+        self.styles.insert(id.clone(), style);
+
+        id
+    }
+
+    pub fn unregister(&mut self, id: &str) -> Option<Style> {
+        self.styles.remove(id)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Style> {
+        self.styles.get(id)
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.styles.contains_key(id)
+    }
+
+    /// the shared id of a registered style
+    pub fn intern(&self, id: &str) -> Option<StyleId> {
+        self.styles.get_key_value(id).map(|(k, _)| k.clone())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&StyleId, &Style)> {
+        self.styles.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.styles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.styles.is_empty()
+    }
+
+    /// check every instance of the scene refers to a registered style
+    pub fn validate(&self, scene: &Scene) -> Result<(), UnknownStyleError> {
+        let instances: Vec<_> = scene
+            .iter()
+            .filter(|v| !self.contains(v.style_id()))
+            .map(|v| (v.pos(), v.style_id().clone()))
+            .collect();
+
+        if instances.is_empty() {
+            Ok(())
+        } else {
+            Err(UnknownStyleError { instances })
+        }
+    }
+
+    /// make the instances of the scene share the ids of this registry,
+    /// instances with unknown style are left untouched
+    pub fn intern_scene(&self, scene: &mut Scene) {
+        let interned: Vec<_> = scene
+            .iter()
+            .filter_map(|v| {
+                let id = self.intern(v.style_id())?;
+                (!Arc::ptr_eq(&id.0, &v.style_id().0)).then(|| Instance::new(v.pos(), id))
+            })
+            .collect();
+
+        // the same styles, so not an edit of the map
+        for v in interned {
+            scene.oc_tree.insert(v);
+        }
+    }
+}
+
+/// Instances referring to styles not in the registry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownStyleError {
+    instances: Vec<(Point3<i32>, StyleId)>,
+}
+
+impl UnknownStyleError {
+    pub fn instances(&self) -> &[(Point3<i32>, StyleId)] {
+        &self.instances
+    }
+}
+
+impl Display for UnknownStyleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "UnknownStyleError-> [{}] instances refer to unknown style",
+            self.instances.len()
+        )?;
+
+        for (pos, id) in self.instances.iter().take(8) {
+            write!(f, "-> [{}] at ({}, {}, {})", id, pos.x, pos.y, pos.z)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for UnknownStyleError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> StyleRegistry {
+        let mut registry = StyleRegistry::new();
+
+        registry.register(
+            "grass",
+            Style::new("cube.obj".to_owned()).with_texture("grass.png".to_owned()),
+        );
+        registry.register(
+            "torch",
+            Style::new("torch.obj".to_owned())
+                .with_passable(true)
+                .with_light(4.0)
+                .with_tint([255, 180, 80, 255]),
+        );
+
+        registry
+    }
+
+    #[test]
+    fn register_and_get() {
+        let mut registry = registry();
+
+        assert_eq!(registry.len(), 2);
+        assert_eq!(registry.get("grass").unwrap().texture(), Some("grass.png"));
+        assert!(registry.get("torch").unwrap().passable());
+        assert_eq!(registry.get("torch").unwrap().light(), 4.0);
+        assert_eq!(registry.get("lava"), None);
+
+        let grass = registry.intern("grass").unwrap();
+        let again = registry.register("grass", Style::new("slab.obj".to_owned()));
+
+        assert!(Arc::ptr_eq(&grass.0, &again.0));
+        assert_eq!(registry.get("grass").unwrap().model(), "slab.obj");
+        assert_eq!(registry.len(), 2);
+    }
+
+    #[test]
+    fn validate_unknown() {
+        let registry = registry();
+        let mut scene = Scene::new();
+
+        scene.insert(Instance::new(Point3 { x: 0, y: 0, z: 0 }, "grass"));
+        scene.insert(Instance::new(Point3 { x: 1, y: 0, z: 0 }, "lava"));
+        scene.insert(Instance::new(Point3 { x: 2, y: 0, z: 0 }, "torch"));
+
+        let e = registry.validate(&scene).unwrap_err();
+
+        assert_eq!(
+            e.instances(),
+            &[(Point3 { x: 1, y: 0, z: 0 }, StyleId::from("lava"))]
+        );
+    }
+
+    #[test]
+    fn intern_scene_share_ids() {
+        let registry = registry();
+        let mut scene = Scene::new();
+
+        for x in 0..10 {
+            scene.insert(Instance::new(Point3 { x, y: 0, z: 0 }, "grass".to_owned()));
+        }
+
+        registry.intern_scene(&mut scene);
+
+        let grass = registry.intern("grass").unwrap();
+
+        assert!(scene.iter().all(|v| Arc::ptr_eq(&v.style_id().0, &grass.0)));
+        assert_eq!(scene.len(), 10);
+    }
+
+    #[test]
+    fn style_json() {
+        let style = registry().get("torch").unwrap().clone();
+        let json = serde_json::to_string(&style).unwrap();
+
+        assert_eq!(serde_json::from_str::<Style>(&json).unwrap(), style);
+    }
+}
@@ -0,0 +1,192 @@
+use super::Scene;
+use cgmath::{InnerSpace, Point3, Vector3};
+use std::collections::HashSet;
+
+/// remove tiles by click, box or region
+pub mod delete;
+/// copy a style onto other tiles
+pub mod format_brush;
+/// place voxels on faces
+pub mod stack;
+
+/// farthest along the ray a drag meets the plane of its stroke, as far as a pick reaches
+pub const DRAG_DISTANCE: f32 = 1000.0;
+/// most cells a stroke spans from where it started, along each axis
+pub const STROKE_REACH: i32 = 64;
+
+/// faces of a cell, as offsets to its neighbours
+const NEIGHBOURS: [Vector3<i32>; 6] = [
+    Vector3::new(1, 0, 0),
+    Vector3::new(-1, 0, 0),
+    Vector3::new(0, 1, 0),
+    Vector3::new(0, -1, 0),
+    Vector3::new(0, 0, 1),
+    Vector3::new(0, 0, -1),
+];
+
+/// Cells of the region sharing the style of the Instance at `pos`,
+/// joined face to face, `pos` first and empty if there's no Instance
+///
+/// # Example
+/// ```
+/// # use scenes::{tools, Instance, Scene};
+/// # use cgmath::Point3;
+/// let mut scene = Scene::new();
+/// for x in 0..3 {
+///     scene.insert(Instance::new(Point3::new(x, 0, 0), "grass"));
+/// }
+/// scene.insert(Instance::new(Point3::new(3, 0, 0), "stone"));
+/// scene.insert(Instance::new(Point3::new(4, 0, 0), "grass"));
+///
+/// assert_eq!(tools::connected(&scene, Point3::new(1, 0, 0)).len(), 3);
+/// ```
+pub fn connected(scene: &Scene, pos: Point3<i32>) -> Vec<Point3<i32>> {
+    let style_id = match scene.get(pos) {
+        Some(v) => v.style_id().clone(),
+        None => return Vec::new(),
+    };
+
+    let mut seen = HashSet::from([pos]);
+    let mut ans = vec![pos];
+    let mut i = 0;
+
+    while let Some(&p) = ans.get(i) {
+        for n in NEIGHBOURS.iter().map(|n| p + n) {
+            if !seen.insert(n) {
+                continue;
+            }
+            if scene.get(n).is_some_and(|v| *v.style_id() == style_id) {
+                ans.push(n);
+            }
+        }
+        i += 1;
+    }
+
+    ans
+}
+
+/// Cell of the plane through the center of `anchor` with `normal`, under the ray,
+/// with the coordinate along the normal kept as the anchor's,
+/// None if the ray is parallel to the plane, or meets it behind or past [`DRAG_DISTANCE`]
+fn plane_cell(
+    anchor: Point3<i32>,
+    normal: Vector3<i32>,
+    origin: Point3<f32>,
+    direction: Vector3<f32>,
+) -> Option<Point3<i32>> {
+    let n = normal.cast::<f32>()?;
+    let center = anchor.cast::<f32>()? + Vector3::new(0.5, 0.5, 0.5);
+
+    let denom = direction.dot(n);
+    if denom.abs() < 1e-6 {
+        return None;
+    }
+    let t = (center - origin).dot(n) / denom;
+    // a ray grazing the plane meets it thousands of cells away
+    if t < 0.0 || t * direction.magnitude() > DRAG_DISTANCE {
+        return None;
+    }
+
+    let at = origin + direction * t;
+    let cell = Point3::new(
+        at.x.floor() as i32,
+        at.y.floor() as i32,
+        at.z.floor() as i32,
+    );
+
+    Some(Point3::new(
+        if normal.x != 0 { anchor.x } else { cell.x },
+        if normal.y != 0 { anchor.y } else { cell.y },
+        if normal.z != 0 { anchor.z } else { cell.z },
+    ))
+}
+
+/// `cell` brought within [`STROKE_REACH`] of `anchor` along each axis
+fn within_reach(anchor: Point3<i32>, cell: Point3<i32>) -> Point3<i32> {
+    let clamp = |a: i32, c: i32| {
+        c.clamp(
+            a.saturating_sub(STROKE_REACH),
+            a.saturating_add(STROKE_REACH),
+        )
+    };
+
+    Point3::new(
+        clamp(anchor.x, cell.x),
+        clamp(anchor.y, cell.y),
+        clamp(anchor.z, cell.z),
+    )
+}
+
+/// every cell of the box between both, included
+fn cells_between(a: Point3<i32>, b: Point3<i32>) -> impl Iterator<Item = Point3<i32>> {
+    let (min, max) = (
+        Point3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+        Point3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+    );
+
+    (min.x..=max.x).flat_map(move |x| {
+        (min.y..=max.y).flat_map(move |y| (min.z..=max.z).map(move |z| Point3::new(x, y, z)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plane_cell_keeps_normal_axis() {
+        let down = Vector3::new(0.0, 0.0, -1.0);
+
+        assert_eq!(
+            plane_cell(
+                Point3::new(0, 0, 3),
+                Vector3::unit_z(),
+                Point3::new(5.5, -2.5, 10.0),
+                down
+            ),
+            Some(Point3::new(5, -3, 3))
+        );
+        // looking along a side face, never meets it
+        assert_eq!(
+            plane_cell(
+                Point3::new(0, 0, 3),
+                Vector3::unit_x(),
+                Point3::new(5.5, -2.5, 10.0),
+                down
+            ),
+            None
+        );
+        assert_eq!(
+            cells_between(Point3::new(1, 0, 0), Point3::new(0, 1, 0)).count(),
+            4
+        );
+    }
+
+    #[test]
+    fn grazing_ray() {
+        let anchor = Point3::new(0, 0, 0);
+
+        // just above the ground, all but parallel to it
+        let grazing = Vector3::new(1.0, 0.0, -1e-5);
+        assert_eq!(
+            plane_cell(
+                anchor,
+                Vector3::unit_z(),
+                Point3::new(0.5, 0.5, 1.0),
+                grazing
+            ),
+            None
+        );
+        // steep enough, it meets the ground within reach of a pick
+        let steep = Vector3::new(1.0, 0.0, -0.01);
+        assert_eq!(
+            plane_cell(anchor, Vector3::unit_z(), Point3::new(0.5, 0.5, 1.0), steep),
+            Some(Point3::new(50, 0, 0))
+        );
+
+        assert_eq!(
+            within_reach(anchor, Point3::new(100_000, -3, i32::MIN)),
+            Point3::new(STROKE_REACH, -3, -STROKE_REACH)
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use super::{connected, plane_cell, within_reach};
+use crate::{History, Instance, Scene};
+use cgmath::{Point3, Vector3};
+
+/// A stroke of the Delete tool
+///
+/// Starts on a picked Instance, removing it.
+/// Dragging removes the box from there to the cell under the pointer,
+/// on the layer it started on, with one walk of the tree each time.
+/// Edits show in the scene as the stroke goes,
+/// and [`Delete::end`] records them as one undo step.
+///
+/// # Example
+/// ```
+/// # use scenes::{tools::delete::Delete, History, Instance, Scene};
+/// # use cgmath::Point3;
+/// let mut scene = Scene::new();
+/// let mut history = History::default();
+/// for x in 0..4 {
+///     for y in 0..4 {
+///         scene.insert(Instance::new(Point3::new(x, y, 0), "stone"));
+///     }
+/// }
+///
+/// let mut stroke = Delete::begin(&mut scene, Point3::new(0, 0, 0));
+/// stroke.drag_to(&mut scene, Point3::new(1, 2, 0));
+/// stroke.end(&mut history);
+///
+/// assert_eq!(scene.len(), 16 - 6);
+///
+/// history.undo(&mut scene);
+/// assert_eq!(scene.len(), 16);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Delete {
+    anchor: Point3<i32>,
+    to: Point3<i32>,
+    /// removed so far
+    removed: Vec<Instance>,
+}
+
+impl Delete {
+    pub fn begin(scene: &mut Scene, pos: Point3<i32>) -> Self {
+        Self {
+            anchor: pos,
+            to: pos,
+            removed: scene.remove(pos).into_iter().collect(),
+        }
+    }
+
+    /// the cell the stroke started at
+    pub fn anchor(&self) -> Point3<i32> {
+        self.anchor
+    }
+
+    /// removed so far
+    pub fn removed(&self) -> &[Instance] {
+        &self.removed
+    }
+
+    /// drag to where the ray meets the layer of the stroke,
+    /// see [`Delete::drag_to`]
+    pub fn drag(&mut self, scene: &mut Scene, origin: Point3<f32>, direction: Vector3<f32>) {
+        if let Some(cell) = plane_cell(self.anchor, Vector3::unit_z(), origin, direction) {
+            self.drag_to(scene, cell);
+        }
+    }
+
+    /// Remove the box from the anchor to `cell` on the anchor's layer,
+    /// put back what's out of it
+    ///
+    /// The box is at most [`super::STROKE_REACH`] cells from the anchor along each axis.
+    pub fn drag_to(&mut self, scene: &mut Scene, cell: Point3<i32>) {
+        let cell = within_reach(self.anchor, cell);
+        self.to = Point3::new(cell.x, cell.y, self.anchor.z);
+        let (min, max) = self.bounds();
+
+        let inside = |p: Point3<i32>| {
+            (min.x..=max.x).contains(&p.x)
+                && (min.y..=max.y).contains(&p.y)
+                && (min.z..=max.z).contains(&p.z)
+        };
+        self.removed.retain(|v| {
+            if inside(v.pos()) {
+                return true;
+            }
+            scene.insert(v.clone());
+            false
+        });
+
+        self.removed.extend(scene.remove_aabb(min, max));
+    }
+
+    /// Record what the stroke removed in the history, as one step
+    pub fn end(self, history: &mut History) {
+        history.record_removed(self.removed);
+    }
+
+    fn bounds(&self) -> (Point3<i32>, Point3<i32>) {
+        let (a, b) = (self.anchor, self.to);
+
+        (
+            Point3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z)),
+            Point3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z)),
+        )
+    }
+}
+
+/// Remove the region connected to pos sharing its style, as one undo step
+pub fn delete_connected(
+    scene: &mut Scene,
+    history: &mut History,
+    pos: Point3<i32>,
+) -> Vec<Instance> {
+    let region = connected(scene, pos);
+
+    history.remove_all(scene, region)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn floor() -> Scene {
+        let mut scene = Scene::new();
+        for x in 0..4 {
+            for y in 0..4 {
+                scene.insert(Instance::new(Point3::new(x, y, 0), "stone"));
+            }
+        }
+        scene.insert(Instance::new(Point3::new(1, 1, 1), "wood"));
+        scene
+    }
+
+    #[test]
+    fn box_within_layer() {
+        let mut scene = floor();
+        let mut history = History::default();
+
+        let mut stroke = Delete::begin(&mut scene, Point3::new(3, 3, 0));
+        stroke.drag_to(&mut scene, Point3::new(0, 0, 5));
+        // the wood above is kept
+        assert_eq!(stroke.removed().len(), 16);
+        assert_eq!(scene.len(), 1);
+
+        stroke.drag_to(&mut scene, Point3::new(3, 2, 0));
+        assert_eq!(stroke.removed().len(), 2);
+        assert_eq!(scene.len(), 15);
+
+        let (before, revision) = (stroke.removed().len(), scene.revision());
+        stroke.end(&mut history);
+        assert_eq!(scene.len(), 17 - before);
+        // recorded as it is, nothing put back and removed again
+        assert_eq!(scene.revision(), revision);
+
+        assert!(history.undo(&mut scene));
+        assert_eq!(scene, floor());
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn click_on_nothing() {
+        let mut scene = floor();
+        let mut history = History::default();
+
+        let stroke = Delete::begin(&mut scene, Point3::new(9, 9, 9));
+        stroke.end(&mut history);
+
+        assert_eq!(scene, floor());
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn connected_region() {
+        let mut scene = floor();
+        let mut history = History::default();
+
+        assert_eq!(
+            delete_connected(&mut scene, &mut history, Point3::new(0, 0, 0)).len(),
+            16
+        );
+        assert_eq!(scene.len(), 1);
+
+        assert!(history.undo(&mut scene));
+        assert_eq!(scene, floor());
+    }
+
+    #[test]
+    fn connected_region_one_edit() {
+        let mut scene = floor();
+        let mut history = History::default();
+        let before = scene.revision();
+
+        delete_connected(&mut scene, &mut history, Point3::new(0, 0, 0));
+
+        assert_eq!(scene.revision(), before + 1);
+    }
+}
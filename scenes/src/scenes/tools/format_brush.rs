@@ -0,0 +1,141 @@
+use super::connected;
+use crate::{History, Scene, StyleId};
+use cgmath::Point3;
+
+/// A stroke of the FormatBrush tool
+///
+/// Copies the style of a source onto every Instance painted, positions kept.
+/// Edits show in the scene as the stroke goes,
+/// and [`FormatBrush::end`] records them as one undo step.
+///
+/// # Example
+/// ```
+/// # use scenes::{tools::format_brush::FormatBrush, History, Instance, Scene};
+/// # use cgmath::Point3;
+/// let mut scene = Scene::new();
+/// let mut history = History::default();
+/// for x in 0..4 {
+///     scene.insert(Instance::new(Point3::new(x, 0, 0), "grass"));
+/// }
+///
+/// let mut stroke = FormatBrush::begin("stone");
+/// stroke.paint(&mut scene, Point3::new(0, 0, 0));
+/// stroke.paint(&mut scene, Point3::new(1, 0, 0));
+/// stroke.end(&mut scene, &mut history);
+///
+/// assert_eq!(scene.get(Point3::new(1, 0, 0)).unwrap().style_id(), "stone");
+/// assert_eq!(scene.get(Point3::new(2, 0, 0)).unwrap().style_id(), "grass");
+///
+/// history.undo(&mut scene);
+/// assert!(scene.iter().all(|v| v.style_id() == "grass"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct FormatBrush {
+    style_id: StyleId,
+    /// restyled so far, with the style before
+    painted: Vec<(Point3<i32>, StyleId)>,
+}
+
+impl FormatBrush {
+    /// begin with the style of the source
+    pub fn begin(style_id: impl Into<StyleId>) -> Self {
+        Self {
+            style_id: style_id.into(),
+            painted: Vec::new(),
+        }
+    }
+
+    pub fn style_id(&self) -> &StyleId {
+        &self.style_id
+    }
+
+    /// positions restyled so far
+    pub fn painted(&self) -> impl Iterator<Item = Point3<i32>> + '_ {
+        self.painted.iter().map(|(p, _)| *p)
+    }
+
+    /// restyle the Instance at pos, false if none or already of the style
+    pub fn paint(&mut self, scene: &mut Scene, pos: Point3<i32>) -> bool {
+        match scene.get(pos) {
+            Some(v) if *v.style_id() != self.style_id => {
+                scene.restyle(pos, self.style_id.clone());
+                self.painted.push((pos, v.style_id().clone()));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// restyle the region connected to pos sharing its style,
+    /// return how many restyled
+    pub fn flood(&mut self, scene: &mut Scene, pos: Point3<i32>) -> usize {
+        connected(scene, pos)
+            .into_iter()
+            .filter(|p| self.paint(scene, *p))
+            .count()
+    }
+
+    /// Put the stroke back and redo it through the history, as one step
+    pub fn end(self, scene: &mut Scene, history: &mut History) {
+        for (pos, from) in self.painted.iter().rev() {
+            scene.restyle(*pos, from.clone());
+        }
+
+        history.begin_group();
+        for (pos, _) in self.painted {
+            history.restyle(scene, pos, self.style_id.clone());
+        }
+        history.end_group();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Instance;
+
+    #[test]
+    fn flood_connected_only() {
+        let mut scene = Scene::new();
+        let mut history = History::default();
+        // a grass L, touching a grass voxel by an edge only
+        for p in [(0, 0, 0), (1, 0, 0), (1, 1, 0), (1, 2, 0)] {
+            scene.insert(Instance::new(Point3::new(p.0, p.1, p.2), "grass"));
+        }
+        scene.insert(Instance::new(Point3::new(2, 3, 0), "grass"));
+        scene.insert(Instance::new(Point3::new(0, 1, 0), "wood"));
+
+        let mut stroke = FormatBrush::begin("stone");
+        assert_eq!(stroke.flood(&mut scene, Point3::new(0, 0, 0)), 4);
+        assert_eq!(stroke.flood(&mut scene, Point3::new(5, 5, 5)), 0);
+        stroke.end(&mut scene, &mut history);
+
+        let styles = |scene: &Scene| {
+            let mut ans: Vec<_> = scene.iter().map(|v| v.style_id().to_string()).collect();
+            ans.sort();
+            ans
+        };
+        assert_eq!(
+            styles(&scene),
+            ["grass", "stone", "stone", "stone", "stone", "wood"]
+        );
+
+        assert!(history.undo(&mut scene));
+        assert_eq!(
+            styles(&scene),
+            ["grass", "grass", "grass", "grass", "grass", "wood"]
+        );
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn paint_twice_once() {
+        let mut scene = Scene::new();
+        scene.insert(Instance::new(Point3::new(0, 0, 0), "grass"));
+
+        let mut stroke = FormatBrush::begin("stone");
+        assert!(stroke.paint(&mut scene, Point3::new(0, 0, 0)));
+        assert!(!stroke.paint(&mut scene, Point3::new(0, 0, 0)));
+        assert_eq!(stroke.painted().count(), 1);
+    }
+}
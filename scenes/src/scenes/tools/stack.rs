@@ -0,0 +1,278 @@
+use super::{cells_between, plane_cell, within_reach};
+use crate::{History, Instance, Scene, StyleId};
+use cgmath::{Point3, Vector3};
+
+/// A stroke of the Stack tool
+///
+/// Starts on a picked face, placing a voxel in the cell in front of it.
+/// Dragging fills the box from there to the cell under the pointer,
+/// on the plane of that face, and only the empty cells of it,
+/// or a straight row of it through the start, see [`Stack::drag_to`].
+/// Edits show in the scene as the stroke goes,
+/// and [`Stack::end`] records them as one undo step.
+///
+/// # Example
+/// ```
+/// # use scenes::{tools::stack::Stack, History, Scene};
+/// # use cgmath::{Point3, Vector3};
+/// let mut scene = Scene::new();
+/// let mut history = History::default();
+///
+/// // on the ground, then dragged 3 cells along +x
+/// let mut stroke = Stack::begin(&mut scene, Point3::new(0, 0, 0), Vector3::unit_z(), "stone");
+/// stroke.drag_to(&mut scene, Point3::new(3, 0, 0), false);
+/// stroke.end(&mut scene, &mut history);
+///
+/// assert_eq!(scene.len(), 4);
+///
+/// history.undo(&mut scene);
+/// assert!(scene.is_empty());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Stack {
+    style_id: StyleId,
+    anchor: Point3<i32>,
+    normal: Vector3<i32>,
+    /// cells the stroke filled so far, empty before it
+    placed: Vec<Point3<i32>>,
+}
+
+impl Stack {
+    /// Begin at the cell in front of a face with that face's normal,
+    /// a zero normal stacks on a layer, as on the ground
+    pub fn begin(
+        scene: &mut Scene,
+        cell: Point3<i32>,
+        normal: Vector3<i32>,
+        style_id: impl Into<StyleId>,
+    ) -> Self {
+        let normal = if normal == Vector3::new(0, 0, 0) {
+            Vector3::unit_z()
+        } else {
+            normal
+        };
+        let mut ans = Self {
+            style_id: style_id.into(),
+            anchor: cell,
+            normal,
+            placed: Vec::new(),
+        };
+
+        ans.fill(scene, cell);
+        ans
+    }
+
+    pub fn style_id(&self) -> &StyleId {
+        &self.style_id
+    }
+
+    /// the cell the stroke started at
+    pub fn anchor(&self) -> Point3<i32> {
+        self.anchor
+    }
+
+    /// cells filled so far
+    pub fn placed(&self) -> &[Point3<i32>] {
+        &self.placed
+    }
+
+    /// drag to where the ray meets the plane of the stroke,
+    /// see [`Stack::drag_to`]
+    pub fn drag(
+        &mut self,
+        scene: &mut Scene,
+        origin: Point3<f32>,
+        direction: Vector3<f32>,
+        row_only: bool,
+    ) {
+        if let Some(cell) = plane_cell(self.anchor, self.normal, origin, direction) {
+            self.drag_to(scene, cell, row_only);
+        }
+    }
+
+    /// Fill the box from the anchor to `cell`, put back what's out of it,
+    /// `row_only` keeps to a row through the anchor: on a side face along the layer
+    /// of the anchor, on a top or bottom face along the axis dragged farther
+    ///
+    /// The box is at most [`super::STROKE_REACH`] cells from the anchor along each axis.
+    pub fn drag_to(&mut self, scene: &mut Scene, cell: Point3<i32>, row_only: bool) {
+        let cell = within_reach(self.anchor, cell);
+        let mut to = Point3::new(
+            if self.normal.x != 0 {
+                self.anchor.x
+            } else {
+                cell.x
+            },
+            if self.normal.y != 0 {
+                self.anchor.y
+            } else {
+                cell.y
+            },
+            if self.normal.z != 0 {
+                self.anchor.z
+            } else {
+                cell.z
+            },
+        );
+        if row_only && self.normal.z == 0 {
+            to.z = self.anchor.z;
+        } else if row_only && (to.x - self.anchor.x).abs() >= (to.y - self.anchor.y).abs() {
+            to.y = self.anchor.y;
+        } else if row_only {
+            to.x = self.anchor.x;
+        }
+
+        let (anchor, min, max) = (
+            self.anchor,
+            Point3::new(
+                self.anchor.x.min(to.x),
+                self.anchor.y.min(to.y),
+                self.anchor.z.min(to.z),
+            ),
+            Point3::new(
+                self.anchor.x.max(to.x),
+                self.anchor.y.max(to.y),
+                self.anchor.z.max(to.z),
+            ),
+        );
+        let inside = |p: &Point3<i32>| {
+            (min.x..=max.x).contains(&p.x)
+                && (min.y..=max.y).contains(&p.y)
+                && (min.z..=max.z).contains(&p.z)
+        };
+
+        self.placed.retain(|p| {
+            if inside(p) {
+                return true;
+            }
+            scene.remove(*p);
+            false
+        });
+
+        for p in cells_between(anchor, to) {
+            self.fill(scene, p);
+        }
+    }
+
+    /// Put the stroke back and redo it through the history, as one step
+    pub fn end(self, scene: &mut Scene, history: &mut History) {
+        for p in &self.placed {
+            scene.remove(*p);
+        }
+
+        history.begin_group();
+        for p in self.placed {
+            history.insert(scene, Instance::new(p, self.style_id.clone()));
+        }
+        history.end_group();
+    }
+
+    fn fill(&mut self, scene: &mut Scene, cell: Point3<i32>) {
+        if scene.get(cell).is_none() {
+            scene.insert(Instance::new(cell, self.style_id.clone()));
+            self.placed.push(cell);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drag_shrinks_and_skips_occupied() {
+        let mut scene = Scene::new();
+        let mut history = History::default();
+        scene.insert(Instance::new(Point3::new(1, 1, 0), "wall"));
+
+        let mut stroke = Stack::begin(&mut scene, Point3::new(0, 0, 0), Vector3::unit_z(), "a");
+        stroke.drag_to(&mut scene, Point3::new(2, 2, 5), false);
+        // a plane on the ground, the wall kept
+        assert_eq!(stroke.placed().len(), 8);
+        assert_eq!(scene.get(Point3::new(1, 1, 0)).unwrap().style_id(), "wall");
+
+        stroke.drag_to(&mut scene, Point3::new(1, 0, 0), false);
+        assert_eq!(stroke.placed().len(), 2);
+        assert_eq!(scene.len(), 3);
+
+        stroke.end(&mut scene, &mut history);
+        assert_eq!(scene.len(), 3);
+        assert!(history.undo(&mut scene));
+        assert_eq!(scene.len(), 1);
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn side_face_plane_and_layer() {
+        let mut scene = Scene::new();
+
+        // on the +x face of a voxel at the origin
+        let mut stroke = Stack::begin(&mut scene, Point3::new(1, 0, 0), Vector3::unit_x(), "a");
+        stroke.drag_to(&mut scene, Point3::new(7, 2, 2), false);
+        assert_eq!(stroke.placed().len(), 9);
+        assert!(stroke.placed().iter().all(|p| p.x == 1));
+
+        stroke.drag_to(&mut scene, Point3::new(7, 2, 2), true);
+        assert_eq!(stroke.placed().len(), 3);
+        assert!(stroke.placed().iter().all(|p| p.x == 1 && p.z == 0));
+    }
+
+    #[test]
+    fn grazing_ray_and_far_cell() {
+        let mut scene = Scene::new();
+
+        let mut stroke = Stack::begin(&mut scene, Point3::new(0, 0, 0), Vector3::unit_z(), "a");
+        // would meet the ground 50000 cells away
+        stroke.drag(
+            &mut scene,
+            Point3::new(0.5, 0.5, 1.0),
+            Vector3::new(1.0, 0.0, -1e-5),
+            false,
+        );
+        assert_eq!(stroke.placed(), &[Point3::new(0, 0, 0)]);
+
+        stroke.drag_to(&mut scene, Point3::new(100_000, 100_000, 0), false);
+        let side = (crate::tools::STROKE_REACH + 1) as usize;
+        assert_eq!(stroke.placed().len(), side * side);
+    }
+
+    #[test]
+    fn top_face_row() {
+        let mut scene = Scene::new();
+
+        let mut stroke = Stack::begin(&mut scene, Point3::new(0, 0, 1), Vector3::unit_z(), "a");
+        stroke.drag_to(&mut scene, Point3::new(3, 1, 1), true);
+        assert_eq!(stroke.placed().len(), 4);
+        assert!(stroke.placed().iter().all(|p| p.y == 0 && p.z == 1));
+
+        stroke.drag_to(&mut scene, Point3::new(-1, -2, 1), true);
+        assert_eq!(stroke.placed().len(), 3);
+        assert!(stroke.placed().iter().all(|p| p.x == 0 && p.z == 1));
+
+        // without Shift, the whole box
+        stroke.drag_to(&mut scene, Point3::new(-1, -2, 1), false);
+        assert_eq!(stroke.placed().len(), 6);
+    }
+
+    #[test]
+    fn drag_by_ray() {
+        let mut scene = Scene::new();
+
+        let mut stroke = Stack::begin(&mut scene, Point3::new(0, 0, 1), Vector3::unit_z(), "a");
+        stroke.drag(
+            &mut scene,
+            Point3::new(2.5, 0.5, 10.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            false,
+        );
+
+        assert_eq!(
+            stroke.placed(),
+            &[
+                Point3::new(0, 0, 1),
+                Point3::new(1, 0, 1),
+                Point3::new(2, 0, 1)
+            ]
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use cgmath::{InnerSpace, Matrix4, Point3, Rad, SquareMatrix, Vector3, Vector4};
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, TAU};
+
+/// vertical field of view
+pub const FOVY: Rad<f32> = Rad(FRAC_PI_4);
+const NEAR: f32 = 0.1;
+const FAR: f32 = 1000.0;
+
+/// radians turned by a pixel dragged
+const ORBIT_SPEED: f32 = 0.01;
+/// distance scaled by e for every this many wheel units
+const ZOOM_UNITS: f32 = 1000.0;
+const MIN_DISTANCE: f32 = 2.0;
+const MAX_DISTANCE: f32 = 500.0;
+/// just above the ground, never under it
+const MIN_PITCH: f32 = 0.05;
+/// straight down
+const MAX_PITCH: f32 = FRAC_PI_2;
+
+/// yaw of the isometric preset, looking from -x -y
+const ISOMETRIC_YAW: f32 = -3.0 * FRAC_PI_4;
+/// yaw of the top-down preset, +y up on screen
+const TOP_DOWN_YAW: f32 = -FRAC_PI_2;
+
+/// Orbit camera around a focus point, +z up
+///
+/// The eye sits `distance` away from `focus`,
+/// turned `yaw` around +z from +x and raised `pitch` above the ground.
+///
+/// # Example
+/// ```
+/// # use sketchpad::Camera;
+/// # use cgmath::Point3;
+/// let mut camera = Camera::top_down(Point3::new(0.0, 0.0, 0.0), 10.0);
+///
+/// camera.zoom(-1000.0);
+/// assert!(camera.distance() < 10.0);
+///
+/// // looking straight down, the eye is right above the focus
+/// let eye = camera.eye();
+/// assert!(eye.x.abs() < 1e-5 && eye.y.abs() < 1e-5 && eye.z > 0.0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    focus: Point3<f32>,
+    yaw: f32,
+    pitch: f32,
+    distance: f32,
+}
+
+impl Camera {
+    pub fn new(focus: Point3<f32>, yaw: f32, pitch: f32, distance: f32) -> Self {
+        Self {
+            focus,
+            yaw: yaw.rem_euclid(TAU),
+            pitch: pitch.clamp(MIN_PITCH, MAX_PITCH),
+            distance: distance.clamp(MIN_DISTANCE, MAX_DISTANCE),
+        }
+    }
+
+    /// true isometric, every axis foreshortened the same
+    pub fn isometric(focus: Point3<f32>, distance: f32) -> Self {
+        Self::new(
+            focus,
+            ISOMETRIC_YAW,
+            (1.0 / 2.0_f32.sqrt()).atan(),
+            distance,
+        )
+    }
+
+    pub fn top_down(focus: Point3<f32>, distance: f32) -> Self {
+        Self::new(focus, TOP_DOWN_YAW, MAX_PITCH, distance)
+    }
+
+    /// isometric, keeping focus and distance
+    pub fn snap_isometric(&mut self) {
+        *self = Self::isometric(self.focus, self.distance);
+    }
+
+    /// top-down, keeping focus and distance
+    pub fn snap_top_down(&mut self) {
+        *self = Self::top_down(self.focus, self.distance);
+    }
+
+    pub fn focus(&self) -> Point3<f32> {
+        self.focus
+    }
+
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    pub fn eye(&self) -> Point3<f32> {
+        self.focus - self.forward() * self.distance
+    }
+
+    /// unit vector the eye looks along
+    pub fn forward(&self) -> Vector3<f32> {
+        let (sy, cy) = self.yaw.sin_cos();
+        let (sp, cp) = self.pitch.sin_cos();
+
+        -Vector3::new(cp * cy, cp * sy, sp)
+    }
+
+    /// unit vector to the right of the screen, always level
+    pub fn right(&self) -> Vector3<f32> {
+        let (sy, cy) = self.yaw.sin_cos();
+
+        Vector3::new(-sy, cy, 0.0)
+    }
+
+    /// unit vector to the top of the screen
+    pub fn up(&self) -> Vector3<f32> {
+        self.right().cross(self.forward())
+    }
+
+    /// turn around the focus by a drag of dx, dy pixels
+    pub fn orbit(&mut self, dx: f32, dy: f32) {
+        *self = Self::new(
+            self.focus,
+            self.yaw - dx * ORBIT_SPEED,
+            self.pitch + dy * ORBIT_SPEED,
+            self.distance,
+        );
+    }
+
+    /// Move the focus by a drag of dx, dy pixels on a view `height` pixels high,
+    /// what's at the focus follows the pointer
+    pub fn pan(&mut self, dx: f32, dy: f32, height: f32) {
+        let per_pixel = 2.0 * self.distance * (FOVY.0 / 2.0).tan() / height.max(1.0);
+
+        self.focus += (self.up() * dy - self.right() * dx) * per_pixel;
+    }
+
+    /// closer for negative wheel units, further for positive
+    pub fn zoom(&mut self, delta: f32) {
+        self.distance =
+            (self.distance * (delta / ZOOM_UNITS).exp()).clamp(MIN_DISTANCE, MAX_DISTANCE);
+    }
+
+    /// closer as fingers spread by `ratio`
+    pub fn pinch(&mut self, ratio: f32) {
+        if ratio > 0.0 {
+            self.distance = (self.distance / ratio).clamp(MIN_DISTANCE, MAX_DISTANCE);
+        }
+    }
+
+    pub fn view(&self) -> Matrix4<f32> {
+        Matrix4::look_to_rh(self.eye(), self.forward(), self.up())
+    }
+
+    /// perspective in OpenGL depth, as cgmath gives
+    pub fn projection(&self, aspect: f32) -> Matrix4<f32> {
+        cgmath::perspective(FOVY, aspect, NEAR, FAR)
+    }
+
+    pub fn view_proj(&self, aspect: f32) -> Matrix4<f32> {
+        self.projection(aspect) * self.view()
+    }
+
+    /// Ray from the near plane through pixel x, y of a view `width` by `height`,
+    /// as origin and unit direction
+    pub fn ray(&self, x: f32, y: f32, width: f32, height: f32) -> (Point3<f32>, Vector3<f32>) {
+        let (width, height) = (width.max(1.0), height.max(1.0));
+        let (nx, ny) = (2.0 * x / width - 1.0, 1.0 - 2.0 * y / height);
+        let inverse = self
+            .view_proj(width / height)
+            .invert()
+            .expect("a perspective view is invertible");
+
+        let unproject = |z: f32| {
+            let p = inverse * Vector4::new(nx, ny, z, 1.0);
+            Point3::from_homogeneous(p)
+        };
+        let (near, far) = (unproject(-1.0), unproject(1.0));
+
+        (near, (far - near).normalize())
+    }
+}
+
+/// isometric at the origin, as a fresh map is seen
+impl Default for Camera {
+    fn default() -> Self {
+        Self::isometric(Point3::new(0.0, 0.0, 0.0), 35.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(c: &Camera, p: Point3<f32>) -> Vector4<f32> {
+        let v = c.view_proj(1.0) * p.to_homogeneous();
+        v / v.w
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn presets() {
+        let iso = Camera::isometric(Point3::new(1.0, 2.0, 0.0), 10.0);
+        let eye = iso.eye() - iso.focus();
+        // as far along every axis, from -x -y and above
+        assert!(close(eye.x, -eye.z) && close(eye.y, -eye.z) && eye.z > 0.0);
+        assert!(close(eye.magnitude(), 10.0));
+
+        let top = Camera::top_down(Point3::new(0.0, 0.0, 0.0), 10.0);
+        let center = project(&top, top.focus());
+        assert!(close(center.x, 0.0) && close(center.y, 0.0));
+        // +y up and +x right on screen
+        assert!(project(&top, Point3::new(0.0, 1.0, 0.0)).y > 0.0);
+        assert!(project(&top, Point3::new(1.0, 0.0, 0.0)).x > 0.0);
+    }
+
+    #[test]
+    fn ray_through_pixel() {
+        let c = Camera::default();
+
+        let (origin, direction) = c.ray(400.0, 300.0, 800.0, 600.0);
+        assert!((direction - c.forward()).magnitude() < 1e-4);
+        // passes the focus
+        let to_focus = c.focus() - origin;
+        assert!(to_focus.cross(direction).magnitude() < 1e-2);
+
+        // a pixel right of the center is right of the focus
+        let (origin, direction) = c.ray(500.0, 300.0, 800.0, 600.0);
+        let at_focus = origin + direction * (c.focus() - origin).dot(c.forward());
+        assert!((at_focus - c.focus()).dot(c.right()) > 0.0);
+    }
+
+    #[test]
+    fn orbit_clamps_pitch() {
+        let mut c = Camera::default();
+
+        c.orbit(0.0, 10_000.0);
+        assert_eq!(c.pitch(), MAX_PITCH);
+        c.orbit(0.0, -10_000.0);
+        assert_eq!(c.pitch(), MIN_PITCH);
+        assert!(c.eye().z > c.focus().z);
+
+        c.orbit(-100.0 * TAU, 0.0);
+        assert!((0.0..TAU).contains(&c.yaw()));
+    }
+
+    #[test]
+    fn zoom_clamps() {
+        let mut c = Camera::default();
+
+        c.zoom(1e9);
+        assert_eq!(c.distance(), MAX_DISTANCE);
+        c.pinch(1e9);
+        assert_eq!(c.distance(), MIN_DISTANCE);
+    }
+
+    #[test]
+    fn pan_follows_pointer() {
+        let height = 600.0;
+        let mut c = Camera::default();
+        let old_focus = c.focus();
+
+        c.pan(30.0, -60.0, height);
+
+        // the old focus went 30 pixels right and 60 up, on a square view
+        let moved = project(&c, old_focus);
+        assert!(close(moved.x, 30.0 * 2.0 / height));
+        assert!(close(moved.y, 60.0 * 2.0 / height));
+    }
+}
@@ -1,22 +1,320 @@
+use scenes::{Scene, Selection, StyleRegistry};
+use std::{cell::RefCell, rc::Rc};
 use yew::prelude::*;
 use yew_canvas::*;
 use web_sys::WebGl2RenderingContext;
 
+/// orbit, pan and zoom of the view
+pub mod camera;
+/// map cells under the pointer
+pub mod pick;
 /// The whole rander pipeline
 mod rander;
 
+pub use camera::Camera;
+pub use pick::{Pick, PickEvent, Stroke};
+
+/// mouse buttons, as `MouseEvent::button` gives
+const LEFT_BUTTON: i16 = 0;
+const MIDDLE_BUTTON: i16 = 1;
+const RIGHT_BUTTON: i16 = 2;
+
+#[derive(Properties, Clone)]
+pub struct Props {
+    /// Drawn as it is, re-meshing the chunks changed since last drawn,
+    /// see [`Scene::changed_since`]
+    pub scene: Rc<RefCell<Scene>>,
+    /// of the scene, to draw again when it changes
+    pub revision: u64,
+    pub styles: Rc<StyleRegistry>,
+    /// highlighted over the map
+    #[prop_or_default]
+    pub selection: Rc<Selection>,
+    /// left button strokes on the map, with what's under the pointer
+    #[prop_or_default]
+    pub on_pick: Callback<PickEvent>,
+}
+
+/// compared by pointer and the revision of the scene, a selection changed is a new Rc
+impl PartialEq for Props {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.scene, &other.scene)
+            && self.revision == other.revision
+            && Rc::ptr_eq(&self.styles, &other.styles)
+            && Rc::ptr_eq(&self.selection, &other.selection)
+            && self.on_pick == other.on_pick
+    }
+}
+
+/// What a pointer held on the view is doing
+#[derive(Debug, Clone, Copy)]
+enum Drag {
+    /// left button, picking for a tool
+    Stroke,
+    Orbit {
+        x: f32,
+        y: f32,
+    },
+    Pan {
+        x: f32,
+        y: f32,
+    },
+    /// two fingers, their middle and how far apart
+    Pinch {
+        x: f32,
+        y: f32,
+        spread: f32,
+    },
+}
+
+/// first two touches, middle and spread of them, or the one touch
+fn touches(e: &TouchEvent) -> Option<Drag> {
+    let list = e.touches();
+    let at = |i| {
+        list.get(i)
+            .map(|t| (t.client_x() as f32, t.client_y() as f32))
+    };
+
+    match (at(0), at(1)) {
+        (Some((x0, y0)), Some((x1, y1))) => Some(Drag::Pinch {
+            x: (x0 + x1) / 2.0,
+            y: (y0 + y1) / 2.0,
+            spread: ((x1 - x0).powi(2) + (y1 - y0).powi(2)).sqrt(),
+        }),
+        (Some((x, y)), None) => Some(Drag::Orbit { x, y }),
+        _ => None,
+    }
+}
+
+/// Map view
+///
+/// - left click or drag: pick, see [`PickEvent`]
+/// - right drag or one finger: orbit
+/// - middle drag, shift + right drag or two fingers: pan
+/// - wheel or pinch: zoom
 #[function_component(Sketchpad)]
-pub fn sketchpad() -> Html {
-    let rander = rander::Rander{};
+pub fn sketchpad(props: &Props) -> Html {
+    let state = use_state(|| Rc::new(RefCell::new(rander::RanderState::default())));
+    let view = use_node_ref();
+    // changed by every event, shown once the view randers again
+    let camera = use_mut_ref(Camera::default);
+    let shown = use_state(Camera::default);
+    let drag = use_mut_ref(|| None::<Drag>);
+
+    let rander = rander::Rander::new(
+        (*state).clone(),
+        props.scene.clone(),
+        props.revision,
+        props.styles.clone(),
+        props.selection.clone(),
+        *shown,
+    );
+
+    let update = {
+        let camera = camera.clone();
+        let shown = shown.clone();
+        move |f: &dyn Fn(&mut Camera)| {
+            f(&mut camera.borrow_mut());
+            shown.set(*camera.borrow());
+        }
+    };
+    let height = {
+        let view = view.clone();
+        move || {
+            view.cast::<web_sys::Element>()
+                .map_or(1.0, |v| v.client_height() as f32)
+        }
+    };
+
+    // left button event at the pointer
+    let pick_event = {
+        let camera = camera.clone();
+        let view = view.clone();
+        let scene = props.scene.clone();
+        move |stroke: Stroke, e: &MouseEvent| {
+            let rect = view.cast::<web_sys::Element>()?.get_bounding_client_rect();
+            let (x, y, width, height) = (
+                e.client_x() as f32 - rect.left() as f32,
+                e.client_y() as f32 - rect.top() as f32,
+                rect.width() as f32,
+                rect.height() as f32,
+            );
+            let camera = camera.borrow();
+
+            Some(PickEvent::new(
+                stroke,
+                camera.ray(x, y, width, height),
+                pick::pick_pixel(scene.borrow().oc_tree(), &camera, x, y, width, height),
+                e.shift_key(),
+            ))
+        }
+    };
+
+    /// the drag to its new place, with how far it went
+    fn moved(drag: &mut Option<Drag>, to: Drag) -> Option<(Drag, f32, f32)> {
+        let delta = match (*drag, to) {
+            (Some(Drag::Orbit { x, y }), Drag::Orbit { x: tx, y: ty })
+            | (Some(Drag::Pan { x, y }), Drag::Pan { x: tx, y: ty })
+            | (Some(Drag::Pinch { x, y, .. }), Drag::Pinch { x: tx, y: ty, .. }) => {
+                Some((tx - x, ty - y))
+            }
+            _ => None,
+        };
+        let from = drag.replace(to)?;
+
+        delta.map(|(dx, dy)| (from, dx, dy))
+    }
+
+    let onmousedown = {
+        let drag = drag.clone();
+        let pick_event = pick_event.clone();
+        let on_pick = props.on_pick.clone();
+        Callback::from(move |e: MouseEvent| {
+            let (x, y) = (e.client_x() as f32, e.client_y() as f32);
+
+            *drag.borrow_mut() = match e.button() {
+                LEFT_BUTTON => {
+                    if let Some(event) = pick_event(Stroke::Begin, &e) {
+                        on_pick.emit(event);
+                    }
+                    Some(Drag::Stroke)
+                }
+                RIGHT_BUTTON if e.shift_key() => Some(Drag::Pan { x, y }),
+                RIGHT_BUTTON => Some(Drag::Orbit { x, y }),
+                MIDDLE_BUTTON => {
+                    e.prevent_default();
+                    Some(Drag::Pan { x, y })
+                }
+                _ => return,
+            };
+        })
+    };
+    let onmousemove = {
+        let drag = drag.clone();
+        let update = update.clone();
+        let height = height.clone();
+        let pick_event = pick_event.clone();
+        let on_pick = props.on_pick.clone();
+        Callback::from(move |e: MouseEvent| {
+            let (x, y) = (e.client_x() as f32, e.client_y() as f32);
+            let to = match *drag.borrow() {
+                Some(Drag::Stroke) => {
+                    if let Some(event) = pick_event(Stroke::Drag, &e) {
+                        on_pick.emit(event);
+                    }
+                    return;
+                }
+                Some(Drag::Orbit { .. }) => Drag::Orbit { x, y },
+                Some(Drag::Pan { .. }) => Drag::Pan { x, y },
+                _ => return,
+            };
+
+            if let Some((_, dx, dy)) = moved(&mut drag.borrow_mut(), to) {
+                match to {
+                    Drag::Orbit { .. } => update(&|c| c.orbit(dx, dy)),
+                    _ => update(&|c| c.pan(dx, dy, height())),
+                }
+            }
+        })
+    };
+    let onmouseup = {
+        let drag = drag.clone();
+        let on_pick = props.on_pick.clone();
+        Callback::from(move |e: MouseEvent| {
+            if let Some(Drag::Stroke) = drag.borrow_mut().take() {
+                if let Some(event) = pick_event(Stroke::End, &e) {
+                    on_pick.emit(event);
+                }
+            }
+        })
+    };
+    let onwheel = {
+        let update = update.clone();
+        Callback::from(move |e: WheelEvent| {
+            e.prevent_default();
+            update(&|c| c.zoom(e.delta_y() as f32));
+        })
+    };
+    let ontouchstart = {
+        let drag = drag.clone();
+        Callback::from(move |e: TouchEvent| {
+            *drag.borrow_mut() = touches(&e);
+        })
+    };
+    let ontouchmove = {
+        let drag = drag.clone();
+        let update = update.clone();
+        let height = height.clone();
+        Callback::from(move |e: TouchEvent| {
+            e.prevent_default();
+            let to = match touches(&e) {
+                Some(to) => to,
+                None => return,
+            };
+
+            match moved(&mut drag.borrow_mut(), to) {
+                Some((Drag::Pinch { spread, .. }, dx, dy)) => {
+                    let ratio = match to {
+                        Drag::Pinch { spread: to, .. } if spread > 0.0 => to / spread,
+                        _ => 1.0,
+                    };
+                    update(&|c| {
+                        c.pan(dx, dy, height());
+                        c.pinch(ratio);
+                    });
+                }
+                Some((_, dx, dy)) => update(&|c| c.orbit(dx, dy)),
+                None => {}
+            }
+        })
+    };
+    let ontouchend = {
+        let drag = drag.clone();
+        Callback::from(move |e: TouchEvent| {
+            *drag.borrow_mut() = touches(&e);
+        })
+    };
+    let onmouseleave = onmouseup.clone();
+    let ontouchcancel = ontouchend.clone();
+    let oncontextmenu = Callback::from(|e: MouseEvent| e.prevent_default());
+
+    let snap_isometric = {
+        let update = update.clone();
+        Callback::from(move |_: MouseEvent| update(&|c| c.snap_isometric()))
+    };
+    let snap_top_down = Callback::from(move |_: MouseEvent| update(&|c| c.snap_top_down()));
+
+    // clicks on presets are not picks
+    let on_presets = Callback::from(|e: MouseEvent| e.stop_propagation());
+    let preset_style = "
+        padding: 2px 6px;
+        border-radius: 5px;
+        cursor: pointer;
+        color: rgb(171, 178, 191);
+        background-color: rgb(33, 37, 43);
+    ";
 
     html!(
         <div
+            ref={view}
             style="
                 width: 100%;
                 height: 100%;
                 background-color: rgb(40, 44, 52);
                 z-index:-1;
+                position: relative;
+                touch-action: none;
             "
+            {onmousedown}
+            {onmousemove}
+            {onmouseup}
+            {onmouseleave}
+            {onwheel}
+            {ontouchstart}
+            {ontouchmove}
+            {ontouchend}
+            {ontouchcancel}
+            {oncontextmenu}
         >
             <Canvas<WebGl2RenderingContext , rander::Rander>
                 //Just use style, canvas can suit automatically.
@@ -26,6 +324,18 @@ pub fn sketchpad() -> Html {
                 "
                 rander={Box::new(rander)}
             />
+            <div
+                style="
+                    position: absolute;
+                    right: 10px;
+                    bottom: 10px;
+                    gap: 5px;
+                "
+                onmousedown={on_presets}
+            >
+                <div style={preset_style} onclick={snap_isometric}>{"Iso"}</div>
+                <div style={preset_style} onclick={snap_top_down}>{"Top"}</div>
+            </div>
         </div>
     )
-}
\ No newline at end of file
+}
@@ -0,0 +1,189 @@
+use crate::Camera;
+use cgmath::{InnerSpace, Point3, Vector3};
+use scenes::{Instance, OcTree};
+
+/// farthest a pick reaches
+pub const PICK_DISTANCE: f32 = 1000.0;
+
+/// A face under the pointer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pick {
+    instance: Option<Instance>,
+    face: Vector3<i32>,
+    adjacent: Point3<i32>,
+}
+
+impl Pick {
+    /// None if the ground under the map was picked
+    pub fn instance(&self) -> Option<&Instance> {
+        self.instance.as_ref()
+    }
+
+    /// normal of the face picked, +z on the ground,
+    /// zero if picked from inside the instance
+    pub fn face(&self) -> Vector3<i32> {
+        self.face
+    }
+
+    /// the cell in front of the face, where a stacked voxel goes
+    pub fn adjacent(&self) -> Point3<i32> {
+        self.adjacent
+    }
+}
+
+/// Where a left button stroke on the view is at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stroke {
+    Begin,
+    Drag,
+    End,
+}
+
+/// A left button press, drag or release on the view
+#[derive(Debug, Clone, PartialEq)]
+pub struct PickEvent {
+    stroke: Stroke,
+    origin: Point3<f32>,
+    direction: Vector3<f32>,
+    pick: Option<Pick>,
+    shift: bool,
+}
+
+impl PickEvent {
+    pub fn new(
+        stroke: Stroke,
+        (origin, direction): (Point3<f32>, Vector3<f32>),
+        pick: Option<Pick>,
+        shift: bool,
+    ) -> Self {
+        Self {
+            stroke,
+            origin,
+            direction,
+            pick,
+            shift,
+        }
+    }
+
+    pub fn stroke(&self) -> Stroke {
+        self.stroke
+    }
+
+    /// ray under the pointer, as origin and unit direction
+    pub fn ray(&self) -> (Point3<f32>, Vector3<f32>) {
+        (self.origin, self.direction)
+    }
+
+    /// what's under the pointer, None if nothing or the sky
+    pub fn pick(&self) -> Option<&Pick> {
+        self.pick.as_ref()
+    }
+
+    pub fn shift(&self) -> bool {
+        self.shift
+    }
+}
+
+/// Instance first hit by the ray, else the ground at z = 0 if the ray goes down to it
+///
+/// # Example
+/// ```
+/// # use scenes::{Instance, OcTree};
+/// # use cgmath::{InnerSpace, Point3, Vector3};
+/// let mut tree = OcTree::from_scope(4);
+/// tree.insert(Instance::new(Point3::new(1, 1, 0), "stone"));
+///
+/// let down = Vector3::new(0.0, 0.0, -1.0);
+///
+/// let hit = sketchpad::pick::pick(&tree, Point3::new(1.5, 1.5, 10.0), down).unwrap();
+/// assert_eq!(hit.instance().map(|v| v.pos()), Some(Point3::new(1, 1, 0)));
+/// assert_eq!(hit.adjacent(), Point3::new(1, 1, 1));
+///
+/// let ground = sketchpad::pick::pick(&tree, Point3::new(-2.5, 0.5, 10.0), down).unwrap();
+/// assert_eq!(ground.instance(), None);
+/// assert_eq!(ground.adjacent(), Point3::new(-3, 0, 0));
+/// ```
+pub fn pick(tree: &OcTree, origin: Point3<f32>, direction: Vector3<f32>) -> Option<Pick> {
+    if let Some(hit) = tree.raycast(origin, direction, PICK_DISTANCE) {
+        let instance = hit.instance().clone();
+
+        return Some(Pick {
+            adjacent: instance.pos() + hit.normal(),
+            face: hit.normal(),
+            instance: Some(instance),
+        });
+    }
+
+    // the ground, from above only
+    if origin.z <= 0.0 || direction.z >= 0.0 {
+        return None;
+    }
+    let t = -origin.z / direction.z;
+    let at = origin + direction * t;
+    if t * direction.magnitude() > PICK_DISTANCE {
+        return None;
+    }
+
+    Some(Pick {
+        instance: None,
+        face: Vector3::unit_z(),
+        adjacent: Point3::new(at.x.floor() as i32, at.y.floor() as i32, 0),
+    })
+}
+
+/// [`pick`] at pixel x, y of a view `width` by `height` seen by the camera
+pub fn pick_pixel(
+    tree: &OcTree,
+    camera: &Camera,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+) -> Option<Pick> {
+    let (origin, direction) = camera.ray(x, y, width, height);
+
+    pick(tree, origin, direction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pick_side_face() {
+        let mut tree = OcTree::from_scope(8);
+        tree.insert(Instance::new(Point3::new(0, 0, 0), "a"));
+        tree.insert(Instance::new(Point3::new(0, 0, 1), "b"));
+
+        let hit = pick(
+            &tree,
+            Point3::new(-5.0, 0.5, 1.5),
+            Vector3::new(1.0, 0.0, 0.0),
+        )
+        .unwrap();
+
+        assert_eq!(
+            hit.instance().map(|v| v.style_id().to_string()),
+            Some("b".to_owned())
+        );
+        assert_eq!(hit.face(), Vector3::new(-1, 0, 0));
+        assert_eq!(hit.adjacent(), Point3::new(-1, 0, 1));
+    }
+
+    #[test]
+    fn pick_through_camera() {
+        let mut tree = OcTree::from_scope(8);
+        tree.insert(Instance::new(Point3::new(2, 3, 0), "a"));
+
+        let camera = Camera::top_down(Point3::new(2.5, 3.5, 1.0), 20.0);
+        let hit = pick_pixel(&tree, &camera, 400.0, 300.0, 800.0, 600.0).unwrap();
+
+        assert_eq!(hit.instance().map(|v| v.pos()), Some(Point3::new(2, 3, 0)));
+        assert_eq!(hit.adjacent(), Point3::new(2, 3, 1));
+
+        // looking at the sky, nothing
+        let mut camera = Camera::isometric(Point3::new(0.0, 0.0, 1.0), 20.0);
+        camera.orbit(0.0, -1000.0);
+        assert_eq!(pick_pixel(&tree, &camera, 400.0, 0.0, 800.0, 600.0), None);
+    }
+}
@@ -1,8 +1,183 @@
-#[derive(Clone, PartialEq)]
-pub(super) struct Rander;
+use crate::Camera;
+use gpu::Gpu;
+use scenes::{Scene, Selection, StyleRegistry};
+use std::{cell::RefCell, rc::Rc};
+use wasm_bindgen_futures::spawn_local;
+
+/// CPU side data the GPU draws
+mod buffer;
+/// wgpu device, surface and pipeline
+mod gpu;
+/// data the shader reads besides vertices
+mod uniform;
+
+const SHADER: &str = include_str!("rander/shader.wgsl");
+
+/// The GPU, made on the first rander as it's async
+#[derive(Default)]
+pub(super) enum GpuState {
+    #[default]
+    Waiting,
+    Creating,
+    Ready(Box<Gpu>),
+    Unsupported,
+}
+
+/// Kept between randers of the canvas
+#[derive(Default)]
+pub(super) struct RanderState {
+    gpu: GpuState,
+    /// id and revision of the map the meshes are made of now, and its styles
+    uploaded: Option<(u64, u64, Rc<StyleRegistry>)>,
+    /// what the highlight is made of now
+    highlighted: Option<Rc<Selection>>,
+}
+
+#[derive(Clone)]
+pub(super) struct Rander {
+    state: Rc<RefCell<RanderState>>,
+    scene: Rc<RefCell<Scene>>,
+    revision: u64,
+    styles: Rc<StyleRegistry>,
+    selection: Rc<Selection>,
+    camera: Camera,
+}
+
+impl Rander {
+    pub(super) fn new(
+        state: Rc<RefCell<RanderState>>,
+        scene: Rc<RefCell<Scene>>,
+        revision: u64,
+        styles: Rc<StyleRegistry>,
+        selection: Rc<Selection>,
+        camera: Camera,
+    ) -> Self {
+        Self {
+            state,
+            scene,
+            revision,
+            styles,
+            selection,
+            camera,
+        }
+    }
+
+    fn draw(&self, canvas: &web_sys::HtmlCanvasElement) {
+        let mut state = self.state.borrow_mut();
+        let RanderState {
+            gpu,
+            uploaded,
+            highlighted,
+        } = &mut *state;
+        let gpu = match gpu {
+            GpuState::Ready(gpu) => gpu,
+            _ => return,
+        };
+
+        gpu.resize(canvas.width(), canvas.height());
+        let (width, height) = gpu.size();
+        gpu.set_camera(&uniform::CameraUniform::from_camera(
+            &self.camera,
+            width as f32 / height as f32,
+        ));
+
+        {
+            let scene = self.scene.borrow();
+            let changed = match uploaded {
+                // a later revision of the same map, only what's changed since
+                Some((id, revision, styles))
+                    if Rc::ptr_eq(styles, &self.styles)
+                        && *id == scene.id()
+                        && *revision <= scene.revision() =>
+                {
+                    scene.changed_since(*revision)
+                }
+                _ => None,
+            };
+            let chunks = match changed {
+                Some(chunks) => chunks,
+                None => {
+                    gpu.clear_chunks();
+                    scene.chunks()
+                }
+            };
+            for chunk in chunks {
+                gpu.set_chunk(
+                    chunk,
+                    &buffer::StyleMesh::from_chunk(&scene, chunk, &self.styles),
+                );
+            }
+            *uploaded = Some((scene.id(), scene.revision(), self.styles.clone()));
+        }
+
+        if !matches!(highlighted, Some(s) if Rc::ptr_eq(s, &self.selection)) {
+            gpu.set_highlight(buffer::StyleMesh::highlight(&self.selection).as_ref());
+            *highlighted = Some(self.selection.clone());
+        }
+
+        match gpu.render() {
+            Ok(()) => {}
+            // the surface goes on next rander after being reconfigured
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                let (width, height) = gpu.size();
+                gpu.resize(0, 0);
+                gpu.resize(width, height);
+            }
+            Err(e) => web_sys::console::error_1(&format!("Rander-> {}", e).into()),
+        }
+    }
+}
+
+/// same state, scene, styles and selection, compared by pointer,
+/// and same revision and camera
+impl PartialEq for Rander {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.state, &other.state)
+            && Rc::ptr_eq(&self.scene, &other.scene)
+            && self.revision == other.revision
+            && Rc::ptr_eq(&self.styles, &other.styles)
+            && Rc::ptr_eq(&self.selection, &other.selection)
+            && self.camera == other.camera
+    }
+}
 
 impl yew_canvas::WithRander for Rander {
     fn rand(self, canvas: &web_sys::HtmlCanvasElement) {
-        
+        let waiting = matches!(self.state.borrow().gpu, GpuState::Waiting);
+
+        if !waiting {
+            self.draw(canvas);
+            return;
+        }
+
+        self.state.borrow_mut().gpu = GpuState::Creating;
+
+        let canvas = canvas.clone();
+        spawn_local(async move {
+            let gpu = match Gpu::new(&canvas).await {
+                Some(gpu) => GpuState::Ready(Box::new(gpu)),
+                None => {
+                    web_sys::console::error_1(&"Rander-> no WebGL2 surface or adapter".into());
+                    GpuState::Unsupported
+                }
+            };
+
+            self.state.borrow_mut().gpu = gpu;
+            self.draw(&canvas);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use naga::valid::{Capabilities, ValidationFlags, Validator};
+
+    #[test]
+    fn shader_valid() {
+        let module = naga::front::wgsl::parse_str(super::SHADER).unwrap();
+
+        Validator::new(ValidationFlags::all(), Capabilities::empty())
+            .validate(&module)
+            .unwrap();
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,242 @@
+use cgmath::Vector3;
+use scenes::{mesh, ChunkPos, Mesh, Scene, Selection, Style, StyleRegistry};
+use std::mem::size_of;
+
+/// color of instances with a style not registered
+pub(crate) const MISSING_STYLE_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 1.0];
+/// see-through amber over what's selected
+pub(crate) const HIGHLIGHT_COLOR: [f32; 4] = [1.0, 0.8, 0.2, 0.45];
+/// how far the highlight stands out of the faces, so it's never behind them
+const HIGHLIGHT_OFFSET: f32 = 0.01;
+
+/// A corner of a mesh
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Vertex {
+    pub(crate) position: [f32; 3],
+    pub(crate) normal: [f32; 3],
+}
+
+// SAFETY: repr(C) of f32 arrays only, no padding
+unsafe impl bytemuck::Zeroable for Vertex {}
+unsafe impl bytemuck::Pod for Vertex {}
+
+impl Vertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3];
+
+    pub(crate) fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Offset and color of a draw, as the shader reads it
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct InstanceRaw {
+    pub(crate) offset: [f32; 3],
+    pub(crate) color: [f32; 4],
+}
+
+// SAFETY: repr(C) of f32 arrays only, no padding
+unsafe impl bytemuck::Zeroable for InstanceRaw {}
+unsafe impl bytemuck::Pod for InstanceRaw {}
+
+impl InstanceRaw {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![2 => Float32x3, 3 => Float32x4];
+
+    pub(crate) fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+pub(crate) fn tint_color(tint: [u8; 4]) -> [f32; 4] {
+    tint.map(|c| c as f32 / 255.0)
+}
+
+/// Vertices and indices of a style, with its color as the one instance
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct StyleMesh {
+    pub(crate) vertices: Vec<Vertex>,
+    pub(crate) indices: Vec<u32>,
+    pub(crate) instance: InstanceRaw,
+}
+
+impl StyleMesh {
+    /// greedy meshes of a chunk, one for each style, colored by its tint
+    pub(crate) fn from_chunk(scene: &Scene, chunk: ChunkPos, styles: &StyleRegistry) -> Vec<Self> {
+        mesh::mesh_aabb(scene.oc_tree(), chunk.min_voxel(), chunk.max_voxel())
+            .into_iter()
+            .map(|(id, m)| Self::new(&m, styles.get(&id)))
+            .collect()
+    }
+
+    /// One shell around every cell selected, None if nothing is
+    ///
+    /// A quad for each face of a cell with no cell selected beside it,
+    /// so the work goes with the selection, however far apart its cells.
+    pub(crate) fn highlight(selection: &Selection) -> Option<Self> {
+        if selection.is_empty() {
+            return None;
+        }
+
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+
+        for p in selection.iter() {
+            for axis in 0..3 {
+                let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+
+                for step in [1, -1] {
+                    let mut offset = Vector3::new(0, 0, 0);
+                    offset[axis] = step;
+                    if selection.contains(p + offset) {
+                        continue;
+                    }
+
+                    let mut normal = [0.0; 3];
+                    normal[axis] = step as f32;
+                    // the far side of the cell up the axis, the near side down it
+                    let plane = if step > 0 { 1.0 } else { 0.0 };
+                    let corner = |du: f32, dv: f32| {
+                        let mut c: [f32; 3] = p.cast::<f32>().unwrap().into();
+                        c[axis] += plane + normal[axis] * HIGHLIGHT_OFFSET;
+                        c[u] += du;
+                        c[v] += dv;
+                        Vertex {
+                            position: c,
+                            normal,
+                        }
+                    };
+
+                    // counter-clockwise seen from outside, u × v points up the axis
+                    let start = vertices.len() as u32;
+                    if step > 0 {
+                        vertices.extend([
+                            corner(0.0, 0.0),
+                            corner(1.0, 0.0),
+                            corner(1.0, 1.0),
+                            corner(0.0, 1.0),
+                        ]);
+                    } else {
+                        vertices.extend([
+                            corner(0.0, 0.0),
+                            corner(0.0, 1.0),
+                            corner(1.0, 1.0),
+                            corner(1.0, 0.0),
+                        ]);
+                    }
+                    indices.extend([0, 1, 2, 0, 2, 3].map(|i| start + i));
+                }
+            }
+        }
+
+        Some(Self {
+            vertices,
+            indices,
+            instance: InstanceRaw {
+                offset: [0.0; 3],
+                color: HIGHLIGHT_COLOR,
+            },
+        })
+    }
+
+    fn new(mesh: &Mesh, style: Option<&Style>) -> Self {
+        Self {
+            vertices: mesh
+                .positions()
+                .iter()
+                .zip(mesh.normals())
+                .map(|(position, normal)| Vertex {
+                    position: *position,
+                    normal: *normal,
+                })
+                .collect(),
+            indices: mesh.indices().to_vec(),
+            instance: InstanceRaw {
+                offset: [0.0; 3],
+                color: style.map_or(MISSING_STYLE_COLOR, |s| tint_color(s.tint())),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{InnerSpace, Point3};
+    use scenes::Instance;
+
+    #[test]
+    fn meshes_from_chunk() {
+        let mut scene = Scene::new();
+        scene.insert(Instance::new(Point3::new(1, 2, 3), "grass"));
+        scene.insert(Instance::new(Point3::new(2, 0, 0), "unknown"));
+        scene.insert(Instance::new(Point3::new(-1, 0, 0), "grass"));
+
+        let mut styles = StyleRegistry::new();
+        styles.register(
+            "grass",
+            Style::new("cube".to_owned()).with_tint([0, 255, 0, 255]),
+        );
+
+        let meshes = StyleMesh::from_chunk(&scene, ChunkPos::new(0, 0, 0), &styles);
+
+        assert_eq!(meshes.len(), 2);
+        assert_eq!(meshes[0].instance.color, [0.0, 1.0, 0.0, 1.0]);
+        assert_eq!(meshes[1].instance.color, MISSING_STYLE_COLOR);
+
+        for m in &meshes {
+            assert_eq!((m.vertices.len(), m.indices.len()), (24, 36));
+            assert!(m
+                .vertices
+                .iter()
+                .all(|v| v.normal.iter().map(|n| n.abs()).sum::<f32>() == 1.0));
+        }
+        assert_eq!(
+            bytemuck::cast_slice::<_, u8>(&meshes[0].vertices).len(),
+            24 * 24
+        );
+    }
+
+    #[test]
+    fn highlight_around_selection() {
+        assert_eq!(StyleMesh::highlight(&Selection::new()), None);
+
+        let mut selection = Selection::new();
+        selection.insert(Point3::new(0, 0, 0));
+        selection.insert(Point3::new(1, 0, 0));
+        let m = StyleMesh::highlight(&selection).unwrap();
+
+        // two cells side by side, the faces between them dropped
+        assert_eq!(m.indices.len(), 10 * 6);
+        assert_eq!(m.instance.color, HIGHLIGHT_COLOR);
+        // out of the faces, along their normals
+        for v in &m.vertices {
+            let axis = v.normal.iter().position(|n| *n != 0.0).unwrap();
+            let out = (v.position[axis] - v.position[axis].round()) * v.normal[axis];
+            assert!((out - HIGHLIGHT_OFFSET).abs() < 1e-6);
+        }
+        // counter-clockwise seen from outside
+        for q in m.vertices.chunks(4) {
+            let [a, b, c] = [0, 1, 2].map(|i| Vector3::from(q[i].position));
+            assert!((b - a).cross(c - a).dot(Vector3::from(q[0].normal)) > 0.0);
+        }
+
+        // far apart, a box each
+        selection.insert(Point3::new(1_000_000, -1_000_000, 0));
+        assert_eq!(
+            StyleMesh::highlight(&selection).unwrap().indices.len(),
+            16 * 6
+        );
+    }
+}
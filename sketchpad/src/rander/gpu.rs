@@ -0,0 +1,334 @@
+use super::{
+    buffer::{InstanceRaw, StyleMesh, Vertex},
+    uniform::CameraUniform,
+};
+use scenes::ChunkPos;
+use std::collections::HashMap;
+use wgpu::util::DeviceExt;
+
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+const CLEAR_COLOR: wgpu::Color = wgpu::Color {
+    r: 40.0 / 255.0,
+    g: 44.0 / 255.0,
+    b: 52.0 / 255.0,
+    a: 1.0,
+};
+
+/// Everything living on the GPU, made once a canvas is there
+pub(crate) struct Gpu {
+    surface: wgpu::Surface,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    config: wgpu::SurfaceConfiguration,
+    depth: wgpu::TextureView,
+    pipeline: wgpu::RenderPipeline,
+    camera: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+    meshes: HashMap<ChunkPos, Vec<GpuMesh>>,
+    /// over the selection, drawn after every chunk
+    highlight: Option<GpuMesh>,
+}
+
+/// A style mesh uploaded
+struct GpuMesh {
+    vertices: wgpu::Buffer,
+    indices: wgpu::Buffer,
+    index_count: u32,
+    /// the one instance, to offset and color it
+    instance: wgpu::Buffer,
+}
+
+impl GpuMesh {
+    fn new(device: &wgpu::Device, mesh: &StyleMesh) -> Self {
+        let init = |label, contents, usage| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents,
+                usage,
+            })
+        };
+
+        Self {
+            vertices: init(
+                "mesh vertices",
+                bytemuck::cast_slice(&mesh.vertices),
+                wgpu::BufferUsages::VERTEX,
+            ),
+            indices: init(
+                "mesh indices",
+                bytemuck::cast_slice(&mesh.indices),
+                wgpu::BufferUsages::INDEX,
+            ),
+            index_count: mesh.indices.len() as u32,
+            instance: init(
+                "mesh instance",
+                bytemuck::bytes_of(&mesh.instance),
+                wgpu::BufferUsages::VERTEX,
+            ),
+        }
+    }
+}
+
+impl Gpu {
+    /// None out of a browser, or if the browser gives no adapter
+    pub(crate) async fn new(canvas: &web_sys::HtmlCanvasElement) -> Option<Self> {
+        let instance = wgpu::Instance::new(wgpu::Backends::all());
+        let surface = create_surface(&instance, canvas)?;
+
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: Some(&surface),
+                force_fallback_adapter: false,
+            })
+            .await?;
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("sketchpad device"),
+                    features: wgpu::Features::empty(),
+                    limits: wgpu::Limits::downlevel_webgl2_defaults()
+                        .using_resolution(adapter.limits()),
+                },
+                None,
+            )
+            .await
+            .ok()?;
+
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: *surface.get_supported_formats(&adapter).first()?,
+            width: canvas.width().max(1),
+            height: canvas.height().max(1),
+            present_mode: wgpu::PresentMode::Fifo,
+        };
+        surface.configure(&device, &config);
+
+        let camera = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("camera"),
+            contents: bytemuck::bytes_of(&CameraUniform::from_camera(
+                &crate::Camera::default(),
+                1.0,
+            )),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let camera_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("camera"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("camera"),
+            layout: &camera_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera.as_entire_binding(),
+            }],
+        });
+
+        let pipeline = create_pipeline(&device, &camera_layout, config.format);
+
+        let depth = create_depth(&device, &config);
+
+        Some(Self {
+            surface,
+            device,
+            queue,
+            config,
+            depth,
+            pipeline,
+            camera,
+            camera_bind_group,
+            meshes: HashMap::new(),
+            highlight: None,
+        })
+    }
+
+    pub(crate) fn size(&self) -> (u32, u32) {
+        (self.config.width, self.config.height)
+    }
+
+    /// reconfigure the surface and depth if the canvas changed size
+    pub(crate) fn resize(&mut self, width: u32, height: u32) {
+        let (width, height) = (width.max(1), height.max(1));
+        if (width, height) == self.size() {
+            return;
+        }
+
+        self.config.width = width;
+        self.config.height = height;
+        self.surface.configure(&self.device, &self.config);
+        self.depth = create_depth(&self.device, &self.config);
+    }
+
+    pub(crate) fn set_camera(&self, camera: &CameraUniform) {
+        self.queue
+            .write_buffer(&self.camera, 0, bytemuck::bytes_of(camera));
+    }
+
+    /// replace the meshes of a chunk, empty ones are skipped
+    pub(crate) fn set_chunk(&mut self, chunk: ChunkPos, meshes: &[StyleMesh]) {
+        let meshes: Vec<_> = meshes
+            .iter()
+            .filter(|m| !m.indices.is_empty())
+            .map(|m| GpuMesh::new(&self.device, m))
+            .collect();
+
+        if meshes.is_empty() {
+            self.meshes.remove(&chunk);
+        } else {
+            self.meshes.insert(chunk, meshes);
+        }
+    }
+
+    /// drop the meshes of every chunk
+    pub(crate) fn clear_chunks(&mut self) {
+        self.meshes.clear();
+    }
+
+    /// replace the selection highlight, None to hide it
+    pub(crate) fn set_highlight(&mut self, mesh: Option<&StyleMesh>) {
+        self.highlight = mesh.map(|m| GpuMesh::new(&self.device, m));
+    }
+
+    pub(crate) fn render(&self) -> Result<(), wgpu::SurfaceError> {
+        let frame = self.surface.get_current_texture()?;
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("sketchpad frame"),
+            });
+
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("voxels"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(CLEAR_COLOR),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &self.camera_bind_group, &[]);
+
+            // see-through, so last
+            for m in self.meshes.values().flatten().chain(&self.highlight) {
+                pass.set_vertex_buffer(0, m.vertices.slice(..));
+                pass.set_vertex_buffer(1, m.instance.slice(..));
+                pass.set_index_buffer(m.indices.slice(..), wgpu::IndexFormat::Uint32);
+                pass.draw_indexed(0..m.index_count, 0, 0..1);
+            }
+        }
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+        frame.present();
+
+        Ok(())
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn create_surface(
+    instance: &wgpu::Instance,
+    canvas: &web_sys::HtmlCanvasElement,
+) -> Option<wgpu::Surface> {
+    Some(instance.create_surface_from_canvas(canvas))
+}
+
+/// the sketchpad only draws in a browser
+#[cfg(not(target_arch = "wasm32"))]
+fn create_surface(_: &wgpu::Instance, _: &web_sys::HtmlCanvasElement) -> Option<wgpu::Surface> {
+    None
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    camera_layout: &wgpu::BindGroupLayout,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("voxel shader"),
+        source: wgpu::ShaderSource::Wgsl(super::SHADER.into()),
+    });
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("voxels"),
+        bind_group_layouts: &[camera_layout],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("voxels"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[Vertex::layout(), InstanceRaw::layout()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            ..Default::default()
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+fn create_depth(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> wgpu::TextureView {
+    device
+        .create_texture(&wgpu::TextureDescriptor {
+            label: Some("depth"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        })
+        .create_view(&wgpu::TextureViewDescriptor::default())
+}
@@ -0,0 +1,67 @@
+use crate::Camera;
+use cgmath::Matrix4;
+
+/// cgmath projects depth to -1..1 as OpenGL, wgpu wants 0..1
+#[rustfmt::skip]
+pub(crate) const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
+);
+
+/// Camera as the shader reads it
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+}
+
+// SAFETY: repr(C) of f32 arrays only, no padding
+unsafe impl bytemuck::Zeroable for CameraUniform {}
+unsafe impl bytemuck::Pod for CameraUniform {}
+
+impl CameraUniform {
+    /// from a view-projection in OpenGL depth, as cgmath gives
+    pub(crate) fn new(view_proj: Matrix4<f32>) -> Self {
+        Self {
+            view_proj: (OPENGL_TO_WGPU_MATRIX * view_proj).into(),
+        }
+    }
+
+    pub(crate) fn from_camera(camera: &Camera, aspect: f32) -> Self {
+        Self::new(camera.view_proj(aspect))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Point3, Vector4};
+
+    fn project(u: &CameraUniform, p: Point3<f32>) -> Vector4<f32> {
+        let c = Matrix4::from(u.view_proj) * p.to_homogeneous();
+        c / c.w
+    }
+
+    #[test]
+    fn wgpu_depth() {
+        let camera = Camera::default();
+        let u = CameraUniform::from_camera(&camera, 2.0);
+
+        let focus = project(&u, camera.focus());
+        assert!(focus.x.abs() < 1e-5 && focus.y.abs() < 1e-5);
+        assert!((0.0..1.0).contains(&focus.z));
+
+        // nearer is smaller depth
+        let nearer = camera.focus() + (camera.eye() - camera.focus()) / 2.0;
+        assert!(project(&u, nearer).z < focus.z);
+    }
+
+    #[test]
+    fn bytes() {
+        let u = CameraUniform::from_camera(&Camera::default(), 1.0);
+
+        assert_eq!(bytemuck::bytes_of(&u).len(), 64);
+    }
+}